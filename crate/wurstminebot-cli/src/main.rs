@@ -3,6 +3,10 @@
 
 use {
     std::{
+        collections::{
+            HashMap,
+            hash_map,
+        },
         future::Future,
         pin::Pin,
         time::{
@@ -10,6 +14,7 @@ use {
             Instant,
         },
     },
+    chrono_tz::Tz,
     discord_message_parser::{
         MessagePart,
         TimestampStyle,
@@ -37,9 +42,12 @@ use {
             voice_state::VoiceStates,
         },
     },
-    sqlx::postgres::{
-        PgConnectOptions,
-        PgPool,
+    sqlx::{
+        postgres::{
+            PgConnectOptions,
+            PgPool,
+        },
+        types::Json,
     },
     systemd_minecraft::{
         VersionSpec,
@@ -56,11 +64,23 @@ use {
         Database,
         Error,
         GENERAL,
-        cal,
+        cal::{
+            self,
+            EventKind,
+            Frequency,
+            Recurrence,
+        },
         config::Config,
         http,
         log,
         minecraft::tellraw,
+        parse,
+        people::{
+            self,
+            PersonId,
+        },
+        sessions,
+        stats,
         twitch,
     },
 };
@@ -132,12 +152,12 @@ impl serenity_utils::handler::voice_state::ExporterMethods for VoiceStateExporte
     }
 }
 
-fn discord_to_minecraft<'a>(ctx: &'a Context, msg: &'a Message, chat: &'a mut Chat, part: MessagePart<'a>) -> Pin<Box<dyn Future<Output = serenity::Result<()>> + Send + 'a>> {
+fn discord_to_minecraft<'a>(ctx: &'a Context, msg: &'a Message, timezone: Tz, chat: &'a mut Chat, part: MessagePart<'a>) -> Pin<Box<dyn Future<Output = serenity::Result<()>> + Send + 'a>> {
     Box::pin(async move {
         match part {
             MessagePart::Empty => {}
             MessagePart::Nested(parts) => for part in parts {
-                discord_to_minecraft(ctx, msg, chat, part).await?;
+                discord_to_minecraft(ctx, msg, timezone, chat, part).await?;
             },
             MessagePart::PlainText(text) => { chat.add_extra(text); }
             MessagePart::UserMention { user, nickname_mention: _ } => {
@@ -183,12 +203,18 @@ fn discord_to_minecraft<'a>(ctx: &'a Context, msg: &'a Message, chat: &'a mut Ch
                 chat.add_extra(format!(":{}:", emoji.name));
             }
             MessagePart::Timestamp { timestamp, style } => {
-                let mut extra = Chat::from(style.unwrap_or_default().fmt(timestamp)); //TODO convert to user timezone? (Would require replacing @a with individual commands)
+                let local_timestamp = timestamp.with_timezone(&timezone);
+                let mut extra = if let Some(TimestampStyle::RelativeTime) = style {
+                    Chat::from(TimestampStyle::RelativeTime.fmt(timestamp))
+                } else {
+                    // the style only selects which fields are shown, so formatting the local time as if it were UTC gives the local representation
+                    Chat::from(style.unwrap_or_default().fmt(local_timestamp.naive_local().and_utc()))
+                };
                 extra.underlined();
                 if let Some(TimestampStyle::RelativeTime) = style {
-                    extra.on_hover(minecraft::chat::HoverEvent::ShowText(Box::new(Chat::from(timestamp.format("%Y-%m-%d %H:%M:%S UTC").to_string())))); //TODO show user timezone if converted
+                    extra.on_hover(minecraft::chat::HoverEvent::ShowText(Box::new(Chat::from(local_timestamp.format("%Y-%m-%d %H:%M:%S %Z").to_string()))));
                 } else {
-                    extra.on_hover(minecraft::chat::HoverEvent::ShowText(Box::new(Chat::from("UTC")))); //TODO show user timzeone if converted
+                    extra.on_hover(minecraft::chat::HoverEvent::ShowText(Box::new(Chat::from(local_timestamp.format("%Z").to_string()))));
                 }
                 chat.add_extra(extra);
             }
@@ -197,12 +223,476 @@ fn discord_to_minecraft<'a>(ctx: &'a Context, msg: &'a Message, chat: &'a mut Ch
     })
 }
 
+/// Converts a Discord message in a world channel to a Minecraft chat message, with timestamps rendered in the given timezone.
+async fn discord_message_to_minecraft(ctx: &Context, msg: &Message, timezone: Tz) -> serenity::Result<Chat> {
+    let mut chat = Chat::from(format!(
+        "[Discord:#{}",
+        if let Channel::Guild(chan) = msg.channel(ctx).await? { chan.name.clone() } else { format!("?") },
+    ));
+    chat.color(minecraft::chat::Color::Aqua);
+    if let Some(ref in_reply_to) = msg.referenced_message {
+        chat.add_extra(", replying to ");
+        chat.add_extra({
+            let mut extra = Chat::from(in_reply_to.member.as_ref().and_then(|member| member.nick.as_deref()).unwrap_or(&in_reply_to.author.name));
+            extra.on_hover(minecraft::chat::HoverEvent::ShowText(Box::new(Chat::from(in_reply_to.author.tag()))));
+            extra
+        });
+    }
+    chat.add_extra("] ");
+    chat.add_extra({
+        let mut extra = Chat::from(format!("<{}>", msg.member.as_ref().and_then(|member| member.nick.as_ref()).unwrap_or(&msg.author.name)));
+        extra.on_hover(minecraft::chat::HoverEvent::ShowText(Box::new(Chat::from(msg.author.tag()))));
+        extra
+    });
+    chat.add_extra(" ");
+    discord_to_minecraft(ctx, msg, timezone, &mut chat, msg.parse()).await?;
+    for attachment in &msg.attachments {
+        chat.add_extra(" ");
+        chat.add_extra({
+            let mut extra = Chat::from(format!("[{}]", attachment.filename));
+            extra.color(minecraft::chat::Color::Blue);
+            extra.underlined();
+            extra.on_click(minecraft::chat::ClickEvent::OpenUrl(attachment.url.clone()));
+            extra.on_hover(minecraft::chat::HoverEvent::ShowText(Box::new(Chat::from(&*attachment.url))));
+            extra
+        });
+    }
+    Ok(chat)
+}
+
+/// Sends a Discord message to the given players, falling back to a short notice if the message is too long for RCON.
+async fn tellraw_discord_message(ctx: &Context, msg: &Message, world: &World, rcpt: &str, chat: &Chat) -> Result<(), Error> {
+    match tellraw(world, rcpt, chat).await {
+        Ok(_) => Ok(()),
+        Err(Error::Minecraft(systemd_minecraft::Error::Rcon(rcon::Error::CommandTooLong))) => {
+            let mut chat = Chat::from(format!(
+                "[Discord:#{}] long message from ",
+                if let Channel::Guild(chan) = msg.channel(ctx).await? { chan.name.clone() } else { format!("?") },
+            ));
+            chat.color(minecraft::chat::Color::Aqua);
+            chat.add_extra({
+                let mut extra = Chat::from(msg.member.as_ref().and_then(|member| member.nick.as_deref()).unwrap_or(&msg.author.name));
+                extra.on_hover(minecraft::chat::HoverEvent::ShowText(Box::new(Chat::from(msg.author.tag()))));
+                extra
+            });
+            tellraw(world, rcpt, &chat).await?;
+            Ok(())
+        }
+        Err(e) => Err(e),
+    }
+}
+
+fn event_kind_subcommands(edit: bool) -> Vec<CreateCommandOption> {
+    let option = |kind: CommandOptionType, name: &str, description: &str, required: bool| CreateCommandOption::new(kind, name, description).required(required && !edit);
+    let subcommand = |name: &str, description: &str, options: Vec<CreateCommandOption>| {
+        let mut subcommand = CreateCommandOption::new(CommandOptionType::SubCommand, name, description);
+        if edit {
+            subcommand = subcommand.add_sub_option(CreateCommandOption::new(
+                CommandOptionType::Integer,
+                "id",
+                "the ID of the event to edit, as shown by /event list",
+            ).required(true));
+        }
+        subcommand = subcommand
+            .add_sub_option(option(CommandOptionType::String, "start", "start time in UTC, e.g. 2024-01-31 18:00", true))
+            .add_sub_option(option(CommandOptionType::String, "end", "end time in UTC, e.g. 2024-01-31 20:00", true));
+        for option in options {
+            subcommand = subcommand.add_sub_option(option);
+        }
+        subcommand
+            .add_sub_option(CreateCommandOption::new(
+                CommandOptionType::String,
+                "repeat",
+                "whether and how often the event repeats",
+            ).required(false)
+                .add_string_choice("never", "never")
+                .add_string_choice("weekly", "weekly")
+                .add_string_choice("monthly", "monthly")
+            )
+            .add_sub_option(CreateCommandOption::new(
+                CommandOptionType::Integer,
+                "every",
+                "repeat every n weeks or months, defaults to 1",
+            ).required(false).min_int_value(1).max_int_value(cal::MAX_INTERVAL.into()))
+            .add_sub_option(CreateCommandOption::new(
+                CommandOptionType::String,
+                "until",
+                "no repetitions start after this time (in UTC), replaces count",
+            ).required(false))
+            .add_sub_option(CreateCommandOption::new(
+                CommandOptionType::Integer,
+                "count",
+                "the total number of occurrences, replaces until",
+            ).required(false).min_int_value(1))
+    };
+    vec![
+        subcommand("minigame", "a Minigame Night", vec![
+            option(CommandOptionType::String, "minigame", "the minigame to be played", true),
+        ]),
+        subcommand("renascence", "a Renascence settlement event", vec![
+            option(CommandOptionType::String, "settlement", "the name of the settlement", true),
+            option(CommandOptionType::Integer, "hub-x", "the x coordinate of the settlement's Nether hub", true),
+            option(CommandOptionType::Integer, "hub-z", "the z coordinate of the settlement's Nether hub", true),
+        ]),
+        subcommand("renascence-dragon-fight", "a Renascence dragon fight", vec![
+            option(CommandOptionType::String, "settlement", "the name of the settlement", true),
+        ]),
+        subcommand("tour", "a server tour", vec![
+            option(CommandOptionType::String, "guests", "mentions or names of the guests, separated by spaces", true),
+            option(CommandOptionType::String, "area", "the area to be toured, defaults to the whole server", false),
+        ]),
+        subcommand("usc", "an Ultra Softcore season", vec![
+            option(CommandOptionType::Integer, "season", "the season number", true),
+        ]),
+        subcommand("other", "any other event", vec![
+            option(CommandOptionType::String, "title", "the name of the event", true),
+            option(CommandOptionType::String, "location", "where the event takes place", false),
+        ]),
+    ]
+}
+
+fn option_value<'a>(options: &'a [CommandDataOption], name: &str) -> Option<&'a CommandDataOptionValue> {
+    options.iter().find(|option| option.name == name).map(|option| &option.value)
+}
+
+fn string_option<'a>(options: &'a [CommandDataOption], name: &str) -> Option<&'a str> {
+    option_value(options, name).map(|value| match value {
+        CommandDataOptionValue::String(value) => &**value,
+        _ => panic!("unexpected slash command option type"),
+    })
+}
+
+fn integer_option(options: &[CommandDataOption], name: &str) -> Option<i64> {
+    option_value(options, name).map(|value| match *value {
+        CommandDataOptionValue::Integer(value) => value,
+        _ => panic!("unexpected slash command option type"),
+    })
+}
+
+/// Builds the event kind for `/event add` or `/event edit`. Options which aren't specified are taken from `prev` if it's an event of the same kind.
+///
+/// The inner `Err` is a message to be shown to the user.
+async fn event_kind(pool: &PgPool, subcommand: &str, options: &[CommandDataOption], prev: Option<&EventKind>) -> sqlx::Result<Result<EventKind, String>> {
+    macro_rules! required {
+        ($value:expr, $name:literal) => {
+            match $value {
+                Some(value) => value,
+                None => return Ok(Err(format!("missing option: {}", $name))),
+            }
+        };
+    }
+
+    Ok(Ok(match subcommand {
+        "minigame" => EventKind::Minigame {
+            minigame: required!(string_option(options, "minigame").map(str::to_owned).or_else(|| if let Some(EventKind::Minigame { minigame }) = prev { Some(minigame.clone()) } else { None }), "minigame"),
+        },
+        "renascence" => {
+            let (prev_settlement, prev_hub_coords) = if let Some(EventKind::Renascence { settlement, hub_coords }) = prev { (Some(settlement), Some(*hub_coords)) } else { (None, None) };
+            let mut hub_coords = [0; 2];
+            for (coord, (idx, name)) in hub_coords.iter_mut().zip([(0, "hub-x"), (1, "hub-z")]) {
+                *coord = required!(match integer_option(options, name) {
+                    Some(value) => match i16::try_from(value) {
+                        Ok(value) => Some(value),
+                        Err(_) => return Ok(Err(format!("{name} is out of range"))),
+                    },
+                    None => prev_hub_coords.map(|prev_hub_coords| prev_hub_coords[idx]),
+                }, "hub coordinates");
+            }
+            EventKind::Renascence {
+                settlement: required!(string_option(options, "settlement").map(str::to_owned).or_else(|| prev_settlement.cloned()), "settlement"),
+                hub_coords,
+            }
+        }
+        "renascence-dragon-fight" => EventKind::RenascenceDragonFight {
+            settlement: required!(string_option(options, "settlement").map(str::to_owned).or_else(|| if let Some(EventKind::RenascenceDragonFight { settlement }) = prev { Some(settlement.clone()) } else { None }), "settlement"),
+        },
+        "tour" => {
+            let (prev_area, prev_guests) = if let Some(EventKind::Tour { area, guests }) = prev { (area.clone(), Some(guests.clone())) } else { (None, None) };
+            let guests = if let Some(mut cmd) = string_option(options, "guests") {
+                let mut guests = Vec::default();
+                loop {
+                    parse::eat_whitespace(&mut cmd);
+                    if cmd.is_empty() { break }
+                    if let Some(guest) = parse::eat_person(&mut cmd, pool).await? {
+                        guests.push(guest);
+                    } else {
+                        return Ok(Err(format!("unknown guest: {}", parse::next_word(cmd).unwrap_or_default())))
+                    }
+                }
+                Some(guests)
+            } else {
+                prev_guests
+            };
+            EventKind::Tour {
+                area: string_option(options, "area").map(str::to_owned).or(prev_area),
+                guests: required!(guests, "guests"),
+            }
+        }
+        "usc" => EventKind::Usc {
+            season: required!(match integer_option(options, "season") {
+                Some(season) => match usize::try_from(season) {
+                    Ok(season) => Some(season),
+                    Err(_) => return Ok(Err(format!("season is out of range"))),
+                },
+                None => if let Some(EventKind::Usc { season }) = prev { Some(*season) } else { None },
+            }, "season"),
+        },
+        "other" => {
+            let (prev_title, prev_location) = if let Some(EventKind::Other { title, location }) = prev { (Some(title.clone()), location.clone()) } else { (None, None) };
+            EventKind::Other {
+                title: required!(string_option(options, "title").map(str::to_owned).or(prev_title), "title"),
+                location: string_option(options, "location").map(str::to_owned).or(prev_location),
+            }
+        }
+        _ => panic!("unexpected slash command subcommand"),
+    }))
+}
+
+/// Builds the recurrence rule for `/event add` or `/event edit`. Options which aren't specified are taken from `prev`.
+///
+/// Since iCalendar doesn't allow a recurrence rule to have both an end time and a count, specifying one of `until` and `count` clears the other.
+///
+/// The `Err` is a message to be shown to the user.
+fn event_recurrence(options: &[CommandDataOption], prev: Option<&Recurrence>) -> Result<Option<Recurrence>, String> {
+    let frequency = match string_option(options, "repeat") {
+        Some("never") => return Ok(None),
+        Some("weekly") => Frequency::Weekly,
+        Some("monthly") => Frequency::Monthly,
+        Some(_) => panic!("unexpected slash command option value"),
+        None => if let Some(prev) = prev {
+            prev.frequency
+        } else if ["every", "until", "count"].into_iter().any(|name| option_value(options, name).is_some()) {
+            return Err(format!("missing option: repeat"))
+        } else {
+            return Ok(None)
+        },
+    };
+    let (until, count) = match (string_option(options, "until"), integer_option(options, "count")) {
+        (Some(_), Some(_)) => return Err(format!("until and count can't be combined, please specify only one of them")),
+        (Some(until), None) => (Some(parse::datetime(until).ok_or_else(|| format!("could not parse until, please use the format YYYY-MM-DD HH:MM (in UTC)"))?), None),
+        (None, Some(count)) => (None, Some(u32::try_from(count).map_err(|_| format!("count is out of range"))?)),
+        (None, None) => (prev.and_then(|prev| prev.until), prev.and_then(|prev| prev.count)),
+    };
+    Ok(Some(Recurrence {
+        frequency,
+        interval: match integer_option(options, "every") {
+            Some(interval) => u32::try_from(interval).ok().filter(|interval| (1..=cal::MAX_INTERVAL).contains(interval)).ok_or_else(|| format!("every must be between 1 and {}", cal::MAX_INTERVAL))?,
+            None => prev.map_or(1, |prev| prev.interval),
+        },
+        until,
+        count,
+        exceptions: prev.map(|prev| prev.exceptions.clone()).unwrap_or_default(),
+    }))
+}
+
+/// Handles the `/event` command. Returns whether the response should be ephemeral, and the response text.
+async fn event_command(pool: &PgPool, options: &[CommandDataOption]) -> sqlx::Result<(bool, String)> {
+    let (subcommand_group, subcommand, options) = match &options[0].value {
+        CommandDataOptionValue::SubCommand(sub_options) => (None, &*options[0].name, &**sub_options),
+        CommandDataOptionValue::SubCommandGroup(subcommands) => match &subcommands[0].value {
+            CommandDataOptionValue::SubCommand(sub_options) => (Some(&*options[0].name), &*subcommands[0].name, &**sub_options),
+            _ => panic!("unexpected slash command option type"),
+        },
+        _ => panic!("unexpected slash command option type"),
+    };
+    let times = |prev: Option<&cal::Event>| {
+        let start_time = match (string_option(options, "start"), prev) {
+            (Some(start_time), _) => parse::datetime(start_time).ok_or_else(|| format!("could not parse start time, please use the format YYYY-MM-DD HH:MM (in UTC)"))?,
+            (None, Some(prev)) => prev.start_time,
+            (None, None) => return Err(format!("missing option: start")),
+        };
+        let end_time = match (string_option(options, "end"), prev) {
+            (Some(end_time), _) => parse::datetime(end_time).ok_or_else(|| format!("could not parse end time, please use the format YYYY-MM-DD HH:MM (in UTC)"))?,
+            (None, Some(prev)) => prev.end_time,
+            (None, None) => return Err(format!("missing option: end")),
+        };
+        if end_time <= start_time {
+            return Err(format!("the event must end after it starts"))
+        }
+        Ok((start_time, end_time))
+    };
+    let event_summary = |event: &cal::Event, title: String| {
+        let mut builder = MessageBuilder::default();
+        builder.push(format!("#{}: ", event.id))
+            .push_bold_safe(title)
+            .push(format!(" (<t:{}:F> to <t:{}:F>", event.start_time.timestamp(), event.end_time.timestamp()));
+        if let Some(Json(ref recurrence)) = event.recurrence {
+            builder.push(match (recurrence.frequency, recurrence.interval) {
+                (Frequency::Weekly, 1) => format!(", repeats weekly"),
+                (Frequency::Weekly, interval) => format!(", repeats every {interval} weeks"),
+                (Frequency::Monthly, 1) => format!(", repeats monthly"),
+                (Frequency::Monthly, interval) => format!(", repeats every {interval} months"),
+            });
+        }
+        builder.push(')').build()
+    };
+    Ok(match (subcommand_group, subcommand) {
+        (Some("add"), kind) => {
+            let (start_time, end_time) = match times(None) {
+                Ok(times) => times,
+                Err(msg) => return Ok((true, msg)),
+            };
+            let kind = match event_kind(pool, kind, options, None).await? {
+                Ok(kind) => kind,
+                Err(msg) => return Ok((true, msg)),
+            };
+            let recurrence = match event_recurrence(options, None) {
+                Ok(recurrence) => recurrence,
+                Err(msg) => return Ok((true, msg)),
+            };
+            let event = cal::Event::add(pool, start_time, end_time, kind, recurrence).await?;
+            (false, format!("added event {}", event_summary(&event, event.title(pool).await)))
+        }
+        (Some("edit"), kind) => {
+            let id = integer_option(options, "id").expect("missing required slash command option");
+            let Some(mut event) = (match i32::try_from(id) { Ok(id) => cal::Event::get(pool, id).await?, Err(_) => None }) else {
+                return Ok((true, format!("there is no event with ID {id}")))
+            };
+            let (start_time, end_time) = match times(Some(&event)) {
+                Ok(times) => times,
+                Err(msg) => return Ok((true, msg)),
+            };
+            let kind = match event_kind(pool, kind, options, Some(&event.kind.0)).await? {
+                Ok(kind) => kind,
+                Err(msg) => return Ok((true, msg)),
+            };
+            let recurrence = match event_recurrence(options, event.recurrence.as_ref().map(|Json(recurrence)| recurrence)) {
+                Ok(recurrence) => recurrence,
+                Err(msg) => return Ok((true, msg)),
+            };
+            event.start_time = start_time;
+            event.end_time = end_time;
+            event.kind = Json(kind);
+            event.recurrence = recurrence.map(Json);
+            event.save(pool).await?;
+            (false, format!("edited event {}", event_summary(&event, event.title(pool).await)))
+        }
+        (None, "cancel") => {
+            let id = integer_option(options, "id").expect("missing required slash command option");
+            let Some(mut event) = (match i32::try_from(id) { Ok(id) => cal::Event::get(pool, id).await?, Err(_) => None }) else {
+                return Ok((true, format!("there is no event with ID {id}")))
+            };
+            if event.cancelled {
+                return Ok((true, format!("event #{} has already been cancelled", event.id)))
+            }
+            let summary = event_summary(&event, event.title(pool).await);
+            event.cancel(pool).await?;
+            (false, format!("cancelled event {summary}"))
+        }
+        (None, "skip") => {
+            let id = integer_option(options, "id").expect("missing required slash command option");
+            let Some(mut event) = (match i32::try_from(id) { Ok(id) => cal::Event::get(pool, id).await?, Err(_) => None }) else {
+                return Ok((true, format!("there is no event with ID {id}")))
+            };
+            let Some(occurrence) = parse::datetime(string_option(options, "occurrence").expect("missing required slash command option")) else {
+                return Ok((true, format!("could not parse occurrence, please use the format YYYY-MM-DD HH:MM (in UTC)")))
+            };
+            if event.recurrence.is_none() {
+                return Ok((true, format!("event #{} doesn't repeat, use /event cancel instead", event.id)))
+            }
+            if !event.occurrences().take_while(|iter_occurrence| iter_occurrence.start_time <= occurrence).any(|iter_occurrence| iter_occurrence.start_time == occurrence) {
+                return Ok((true, format!("event #{} has no occurrence starting at <t:{}:F>", event.id, occurrence.timestamp())))
+            }
+            if let Some(Json(ref mut recurrence)) = event.recurrence {
+                recurrence.exceptions.push(occurrence);
+            }
+            event.save(pool).await?;
+            (false, format!("skipped the occurrence of {} at <t:{}:F>", event_summary(&event, event.title(pool).await), occurrence.timestamp()))
+        }
+        (None, "list") => {
+            let mut response = String::default();
+            for event in cal::Event::upcoming(pool).await? {
+                let line = event_summary(&event, event.title(pool).await);
+                if response.len() + line.len() + 1 > 2000 { break } // Discord message length limit
+                response.push_str(&line);
+                response.push('\n');
+            }
+            (true, if response.is_empty() { format!("no upcoming events") } else { response })
+        }
+        _ => panic!("unexpected slash command subcommand"),
+    })
+}
+
+/// Appends a part of a response unless that would exceed the Discord message length limit. Returns whether the part was appended.
+fn push_within_limit(response: &mut MessageBuilder, part: &mut MessageBuilder) -> bool {
+    if response.0.len() + part.0.len() > 2000 { return false } // Discord message length limit
+    response.push(part.build());
+    true
+}
+
+/// Appends the per-world leaderboards from [`stats::death_leaderboard`] or [`stats::advancement_leaderboard`] to a response, showing the top 5 players of each world. Worlds which don't fit in the message are omitted.
+fn push_leaderboards(response: &mut MessageBuilder, leaderboard: Vec<(String, String, i64)>) {
+    for (world, entries) in &leaderboard.into_iter().chunk_by(|(world, _, _)| world.clone()) {
+        let mut section = MessageBuilder::default();
+        section.push("**").push_safe(world).push_line("**");
+        for (rank, (_, player, count)) in entries.take(5).enumerate() {
+            section.push(format!("{}. ", rank + 1)).push_safe(player).push_line(format!(": {count}"));
+        }
+        if !push_within_limit(response, &mut section) { break }
+    }
+}
+
+async fn deaths_command(pool: &PgPool, player: Option<&str>) -> sqlx::Result<String> {
+    let by_cause = stats::deaths_by_cause(pool, player).await?;
+    let recent = stats::recent_deaths(pool, player, 5).await?;
+    if recent.is_empty() {
+        let mut response = MessageBuilder::default();
+        response.push("no deaths recorded");
+        if let Some(player) = player {
+            response.push(" for ").push_safe(player);
+        }
+        return Ok(response.build())
+    }
+    let mut response = MessageBuilder::default();
+    if !by_cause.is_empty() {
+        response.push_line("**by cause**");
+        for (key, count) in by_cause.into_iter().take(10) {
+            response.push_mono_safe(key.strip_prefix("death.").unwrap_or(&key)).push_line(format!(": {count}"));
+        }
+    }
+    response.push_line("**most recent**");
+    for death in recent {
+        if !push_within_limit(&mut response, MessageBuilder::default().push(format!("<t:{}:R> in ", death.timestamp.timestamp())).push_safe(death.world).push(": ").push_line_safe(death.msg)) { break }
+    }
+    push_leaderboards(&mut response, stats::death_leaderboard(pool, player).await?);
+    Ok(response.build())
+}
+
+async fn advancements_command(pool: &PgPool, player: Option<&str>) -> sqlx::Result<String> {
+    let by_kind = stats::advancements_by_kind(pool, player).await?;
+    let recent = stats::recent_advancements(pool, player, 5).await?;
+    if recent.is_empty() {
+        let mut response = MessageBuilder::default();
+        response.push("no advancements recorded");
+        if let Some(player) = player {
+            response.push(" for ").push_safe(player);
+        }
+        return Ok(response.build())
+    }
+    let mut response = MessageBuilder::default();
+    response.push_line("**by kind**");
+    for (kind, count) in by_kind {
+        response.push_safe(kind).push_line(format!(": {count}"));
+    }
+    response.push_line("**most recent**");
+    for advancement in recent {
+        if !push_within_limit(&mut response, MessageBuilder::default().push(format!("<t:{}:R> in ", advancement.timestamp.timestamp())).push_safe(advancement.world).push(": ").push_safe(advancement.player).push(" [").push_safe(advancement.advancement).push_line(']')) { break }
+    }
+    push_leaderboards(&mut response, stats::advancement_leaderboard(pool, player).await?);
+    Ok(response.build())
+}
+
 #[derive(Clone, Copy)]
 struct CommandIds {
-    //TODO `/event` any-admin command to add or edit calendar events
+    advancements: CommandId,
+    deaths: CommandId,
+    event: CommandId,
     iam: CommandId,
     iamn: CommandId,
+    online: CommandId,
     ping: CommandId,
+    playtime: CommandId,
+    timezone: CommandId,
     update: CommandId,
     veto: CommandId,
 }
@@ -225,63 +715,118 @@ async fn main() -> Result<serenity_utils::Builder, Error> {
         }))
         .on_message(true, |ctx, msg| Box::pin(async move {
             if msg.author.bot { return Ok(()) } // ignore bots to prevent message loops
-            if let Some((world_name, _)) = ctx.data.read().await.get::<Config>().expect("missing config").wurstminebot.world_channels.iter().find(|(_, &chan_id)| chan_id == msg.channel_id) {
-                if Command::new("systemctl").arg("is-active").arg(format!("minecraft@{world_name}.service")).status().await?.success() {
-                    let mut chat = Chat::from(format!(
-                        "[Discord:#{}",
-                        if let Channel::Guild(chan) = msg.channel(&ctx).await? { chan.name.clone() } else { format!("?") },
-                    ));
-                    chat.color(minecraft::chat::Color::Aqua);
-                    if let Some(ref in_reply_to) = msg.referenced_message {
-                        chat.add_extra(", replying to ");
-                        chat.add_extra({
-                            let mut extra = Chat::from(in_reply_to.member.as_ref().and_then(|member| member.nick.as_deref()).unwrap_or(&in_reply_to.author.name));
-                            extra.on_hover(minecraft::chat::HoverEvent::ShowText(Box::new(Chat::from(in_reply_to.author.tag()))));
-                            extra
-                        });
-                    }
-                    chat.add_extra("] ");
-                    chat.add_extra({
-                        let mut extra = Chat::from(format!("<{}>", msg.member.as_ref().and_then(|member| member.nick.as_ref()).unwrap_or(&msg.author.name)));
-                        extra.on_hover(minecraft::chat::HoverEvent::ShowText(Box::new(Chat::from(msg.author.tag()))));
-                        extra
-                    });
-                    chat.add_extra(" ");
-                    discord_to_minecraft(&ctx, &msg, &mut chat, msg.parse()).await?;
-                    for attachment in &msg.attachments {
-                        chat.add_extra(" ");
-                        chat.add_extra({
-                            let mut extra = Chat::from(format!("[{}]", attachment.filename));
-                            extra.color(minecraft::chat::Color::Blue);
-                            extra.underlined();
-                            extra.on_click(minecraft::chat::ClickEvent::OpenUrl(attachment.url.clone()));
-                            extra.on_hover(minecraft::chat::HoverEvent::ShowText(Box::new(Chat::from(&*attachment.url))));
-                            extra
-                        });
-                    }
-                    match tellraw(&World::new(world_name), "@a", &chat).await {
-                        Ok(_) => {}
-                        Err(Error::Minecraft(systemd_minecraft::Error::Rcon(rcon::Error::CommandTooLong))) => {
-                            let mut chat = Chat::from(format!(
-                                "[Discord:#{}] long message from ",
-                                if let Channel::Guild(chan) = msg.channel(&ctx).await? { chan.name.clone() } else { format!("?") },
-                            ));
-                            chat.color(minecraft::chat::Color::Aqua);
-                            chat.add_extra({
-                                let mut extra = Chat::from(msg.member.as_ref().and_then(|member| member.nick.as_deref()).unwrap_or(&msg.author.name));
-                                extra.on_hover(minecraft::chat::HoverEvent::ShowText(Box::new(Chat::from(msg.author.tag()))));
-                                extra
-                            });
-                            tellraw(&World::new(world_name), "@a", &chat).await?;
-                        }
-                        Err(e) => return Err(e.into()),
-                    }
+            let (world_name, pool) = {
+                let data = ctx.data.read().await;
+                let Some((world_name, _)) = data.get::<Config>().expect("missing config").wurstminebot.world_channels.iter().find(|(_, &chan_id)| chan_id == msg.channel_id) else { return Ok(()) };
+                (world_name.clone(), data.get::<Database>().expect("missing database connection").clone())
+            };
+            if Command::new("systemctl").arg("is-active").arg(format!("minecraft@{world_name}.service")).status().await?.success() {
+                let world = World::new(&world_name);
+                // timestamps are rendered in each player's timezone, so the message is sent to each player individually
+                let mut chats = HashMap::new();
+                let players = wurstminebot::minecraft::list(&world).await?;
+                for player in &players {
+                    let timezone = people::minecraft_timezone(&pool, player).await?.unwrap_or(Tz::UTC);
+                    let chat = match chats.entry(timezone) {
+                        hash_map::Entry::Occupied(entry) => entry.into_mut(),
+                        hash_map::Entry::Vacant(entry) => entry.insert(discord_message_to_minecraft(&ctx, &msg, timezone).await?),
+                    };
+                    tellraw_discord_message(&ctx, &msg, &world, player, chat).await?;
                 }
+                // players who joined after the player list was requested get the message in UTC
+                let chat = match chats.entry(Tz::UTC) {
+                    hash_map::Entry::Occupied(entry) => entry.into_mut(),
+                    hash_map::Entry::Vacant(entry) => entry.insert(discord_message_to_minecraft(&ctx, &msg, Tz::UTC).await?),
+                };
+                tellraw_discord_message(&ctx, &msg, &world, &wurstminebot::minecraft::unlisted_players(&players), chat).await?;
             }
             Ok(())
         }))
         .on_guild_create(false, |ctx, guild, _| Box::pin(async move {
             let mut commands = Vec::default();
+            let advancements = {
+                let idx = commands.len();
+                commands.push(CreateCommand::new("advancements")
+                    .kind(CommandType::ChatInput)
+                    .add_context(InteractionContext::Guild)
+                    .description("Show statistics about advancements made in Minecraft")
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::String,
+                        "player",
+                        "the Minecraft nickname of the player to show, defaults to all players",
+                    ).required(false))
+                );
+                idx
+            };
+            let deaths = {
+                let idx = commands.len();
+                commands.push(CreateCommand::new("deaths")
+                    .kind(CommandType::ChatInput)
+                    .add_context(InteractionContext::Guild)
+                    .description("Show statistics about deaths in Minecraft")
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::String,
+                        "player",
+                        "the Minecraft nickname of the player to show, defaults to all players",
+                    ).required(false))
+                );
+                idx
+            };
+            let event = {
+                let idx = commands.len();
+                let mut add = CreateCommandOption::new(
+                    CommandOptionType::SubCommandGroup,
+                    "add",
+                    "Add an event to the calendar",
+                );
+                for subcommand in event_kind_subcommands(false) {
+                    add = add.add_sub_option(subcommand);
+                }
+                let mut edit = CreateCommandOption::new(
+                    CommandOptionType::SubCommandGroup,
+                    "edit",
+                    "Edit a calendar event, options which aren't specified are left unchanged",
+                );
+                for subcommand in event_kind_subcommands(true) {
+                    edit = edit.add_sub_option(subcommand);
+                }
+                commands.push(CreateCommand::new("event")
+                    .kind(CommandType::ChatInput)
+                    .add_context(InteractionContext::Guild)
+                    .default_member_permissions(Permissions::ADMINISTRATOR)
+                    .description("Manage the event calendar")
+                    .add_option(add)
+                    .add_option(edit)
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::SubCommand,
+                        "cancel",
+                        "Remove an event from the calendar",
+                    ).add_sub_option(CreateCommandOption::new(
+                        CommandOptionType::Integer,
+                        "id",
+                        "the ID of the event to cancel, as shown by /event list",
+                    ).required(true)))
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::SubCommand,
+                        "skip",
+                        "Skip one occurrence of a repeating event",
+                    ).add_sub_option(CreateCommandOption::new(
+                        CommandOptionType::Integer,
+                        "id",
+                        "the ID of the event, as shown by /event list",
+                    ).required(true)).add_sub_option(CreateCommandOption::new(
+                        CommandOptionType::String,
+                        "occurrence",
+                        "the start time of the occurrence to skip, in UTC",
+                    ).required(true)))
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::SubCommand,
+                        "list",
+                        "Show upcoming events along with their IDs",
+                    ))
+                );
+                idx
+            };
             let iam = {
                 let idx = commands.len();
                 commands.push(CreateCommand::new("iam")
@@ -310,6 +855,15 @@ async fn main() -> Result<serenity_utils::Builder, Error> {
                 );
                 idx
             };
+            let online = {
+                let idx = commands.len();
+                commands.push(CreateCommand::new("online")
+                    .kind(CommandType::ChatInput)
+                    .add_context(InteractionContext::Guild)
+                    .description("Show who is currently playing in this channel's Minecraft world")
+                );
+                idx
+            };
             let ping = {
                 let idx = commands.len();
                 commands.push(CreateCommand::new("ping")
@@ -319,6 +873,34 @@ async fn main() -> Result<serenity_utils::Builder, Error> {
                 );
                 idx
             };
+            let playtime = {
+                let idx = commands.len();
+                commands.push(CreateCommand::new("playtime")
+                    .kind(CommandType::ChatInput)
+                    .add_context(InteractionContext::Guild)
+                    .description("Show who has played the most")
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::String,
+                        "world",
+                        "only count playtime in this world, defaults to all worlds",
+                    ).required(false))
+                );
+                idx
+            };
+            let timezone = {
+                let idx = commands.len();
+                commands.push(CreateCommand::new("timezone")
+                    .kind(CommandType::ChatInput)
+                    .add_context(InteractionContext::Guild)
+                    .description("Set the timezone in which times are shown to you in Minecraft")
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::String,
+                        "timezone",
+                        "a name from the tz database, e.g. Europe/Berlin; defaults to UTC",
+                    ).required(false))
+                );
+                idx
+            };
             let update = {
                 let idx = commands.len();
                 commands.push(CreateCommand::new("update")
@@ -349,9 +931,15 @@ async fn main() -> Result<serenity_utils::Builder, Error> {
             };
             let commands = guild.set_commands(ctx, commands).await?;
             ctx.data.write().await.insert::<CommandIds>(CommandIds {
+                advancements: commands[advancements].id,
+                deaths: commands[deaths].id,
+                event: commands[event].id,
                 iam: commands[iam].id,
                 iamn: commands[iamn].id,
+                online: commands[online].id,
                 ping: commands[ping].id,
+                playtime: commands[playtime].id,
+                timezone: commands[timezone].id,
                 update: commands[update].id,
                 veto: commands[veto].id,
             });
@@ -361,7 +949,37 @@ async fn main() -> Result<serenity_utils::Builder, Error> {
             match interaction {
                 Interaction::Command(interaction) => {
                     if let Some(&command_ids) = ctx.data.read().await.get::<CommandIds>() {
-                        if interaction.data.id == command_ids.iam {
+                        if interaction.data.id == command_ids.advancements {
+                            let response = {
+                                let data = ctx.data.read().await;
+                                let pool = data.get::<Database>().expect("missing database connection");
+                                advancements_command(pool, string_option(&interaction.data.options, "player")).await?
+                            };
+                            interaction.create_response(ctx, CreateInteractionResponse::Message(CreateInteractionResponseMessage::new()
+                                .ephemeral(false)
+                                .content(response)
+                            )).await?;
+                        } else if interaction.data.id == command_ids.deaths {
+                            let response = {
+                                let data = ctx.data.read().await;
+                                let pool = data.get::<Database>().expect("missing database connection");
+                                deaths_command(pool, string_option(&interaction.data.options, "player")).await?
+                            };
+                            interaction.create_response(ctx, CreateInteractionResponse::Message(CreateInteractionResponseMessage::new()
+                                .ephemeral(false)
+                                .content(response)
+                            )).await?;
+                        } else if interaction.data.id == command_ids.event {
+                            let (ephemeral, response) = {
+                                let data = ctx.data.read().await;
+                                let pool = data.get::<Database>().expect("missing database connection");
+                                event_command(pool, &interaction.data.options).await?
+                            };
+                            interaction.create_response(ctx, CreateInteractionResponse::Message(CreateInteractionResponseMessage::new()
+                                .ephemeral(ephemeral)
+                                .content(response)
+                            )).await?;
+                        } else if interaction.data.id == command_ids.iam {
                             let member = interaction.member.clone().expect("/iam called outside of a guild");
                             let role_id = match interaction.data.options[0].value {
                                 CommandDataOptionValue::Role(role) => role,
@@ -397,6 +1015,45 @@ async fn main() -> Result<serenity_utils::Builder, Error> {
                                 .ephemeral(true)
                                 .content(response)
                             )).await?;
+                        } else if interaction.data.id == command_ids.online {
+                            let world_and_pool = {
+                                let data = ctx.data.read().await;
+                                data.get::<Config>().expect("missing config").wurstminebot.world_channels.iter()
+                                    .find(|(_, &chan_id)| chan_id == interaction.channel_id)
+                                    .map(|(world_name, _)| (world_name.clone(), data.get::<Database>().expect("missing database connection").clone()))
+                            };
+                            let response = if let Some((world_name, pool)) = world_and_pool {
+                                match wurstminebot::minecraft::list(&World::new(&world_name)).await {
+                                    Ok(players) => if players.is_empty() {
+                                        format!("nobody is online")
+                                    } else {
+                                        let mut builder = MessageBuilder::default();
+                                        builder.push(format!("{} online: ", players.len()));
+                                        for (idx, player) in players.into_iter().enumerate() {
+                                            if idx > 0 { builder.push(", "); }
+                                            if let Some(user_id) = people::from_minecraft(&pool, &player).await? {
+                                                let name = PersonId::Discord(user_id).display(&pool).await;
+                                                if name == player {
+                                                    builder.push_safe(player);
+                                                } else {
+                                                    builder.push_safe(name).push(" (").push_safe(player).push(')');
+                                                }
+                                            } else {
+                                                builder.push_safe(player);
+                                            }
+                                        }
+                                        builder.build()
+                                    },
+                                    Err(Error::Minecraft(_)) => format!("this world is not running"),
+                                    Err(e) => return Err(e.into()),
+                                }
+                            } else {
+                                format!("This channel has no associated Minecraft world.")
+                            };
+                            interaction.create_response(ctx, CreateInteractionResponse::Message(CreateInteractionResponseMessage::new()
+                                .ephemeral(true)
+                                .content(response)
+                            )).await?;
                         } else if interaction.data.id == command_ids.ping {
                             interaction.create_response(ctx, CreateInteractionResponse::Message(CreateInteractionResponseMessage::new()
                                 .ephemeral(true)
@@ -409,6 +1066,54 @@ async fn main() -> Result<serenity_utils::Builder, Error> {
                                     }
                                 })
                             )).await?;
+                        } else if interaction.data.id == command_ids.playtime {
+                            let world = interaction.data.options.get(0).map(|option| match &option.value {
+                                CommandDataOptionValue::String(world) => &**world,
+                                _ => panic!("unexpected slash command option type"),
+                            });
+                            let leaderboard = {
+                                let data = ctx.data.read().await;
+                                let pool = data.get::<Database>().expect("missing database connection");
+                                sessions::leaderboard(pool, world).await?
+                            };
+                            let mut response = MessageBuilder::default();
+                            if leaderboard.is_empty() {
+                                response.push("no playtime recorded");
+                                if let Some(world) = world {
+                                    response.push(" in world ").push_safe(world);
+                                }
+                            } else {
+                                for (rank, (minecraft_nick, playtime)) in leaderboard.into_iter().take(10).enumerate() {
+                                    response.push(format!("{}. ", rank + 1)).push_safe(minecraft_nick).push(": ").push_line(sessions::format_playtime(playtime));
+                                }
+                            }
+                            interaction.create_response(ctx, CreateInteractionResponse::Message(CreateInteractionResponseMessage::new()
+                                .ephemeral(false)
+                                .content(response.build())
+                            )).await?;
+                        } else if interaction.data.id == command_ids.timezone {
+                            let timezone = interaction.data.options.get(0).map(|option| match &option.value {
+                                CommandDataOptionValue::String(timezone) => timezone.parse::<Tz>().map_err(|_| timezone),
+                                _ => panic!("unexpected slash command option type"),
+                            }).transpose();
+                            let response = match timezone {
+                                Ok(timezone) => {
+                                    let data = ctx.data.read().await;
+                                    let pool = data.get::<Database>().expect("missing database connection");
+                                    if !people::set_timezone(pool, interaction.user.id, timezone).await? {
+                                        format!("you're not in the Wurstmineberg people database")
+                                    } else if let Some(timezone) = timezone {
+                                        MessageBuilder::default().push("timezone set to ").push_safe(timezone.name()).build()
+                                    } else {
+                                        format!("timezone reset to UTC")
+                                    }
+                                }
+                                Err(timezone) => MessageBuilder::default().push("unknown timezone: ").push_mono_safe(timezone).build(),
+                            };
+                            interaction.create_response(ctx, CreateInteractionResponse::Message(CreateInteractionResponseMessage::new()
+                                .ephemeral(true)
+                                .content(response)
+                            )).await?;
                         } else if interaction.data.id == command_ids.update {
                             if let Some((world_name, _)) = ctx.data.read().await.get::<Config>().expect("missing config").wurstminebot.world_channels.iter().find(|(_, &chan_id)| chan_id == interaction.channel_id) {
                                 let version_spec = if let Some(option) = interaction.data.options.get(0) {
@@ -465,7 +1170,35 @@ async fn main() -> Result<serenity_utils::Builder, Error> {
                         }
                     }
                 }
-                Interaction::Component(_) => panic!("received message component interaction even though no message components are registered"),
+                Interaction::Component(interaction) => if let Some((event_id, rsvp)) = cal::Rsvp::from_custom_id(&interaction.data.custom_id) {
+                    let pool = ctx.data.read().await.get::<Database>().expect("missing database connection").clone();
+                    if let Some(event) = cal::Event::get(&pool, event_id).await?.filter(|event| !event.cancelled) {
+                        // each announcement is for a specific occurrence of a recurring event
+                        if let Some(event) = event.announced_occurrence(&pool, interaction.message.id).await? {
+                            event.set_rsvp(&pool, &PersonId::Discord(interaction.user.id), rsvp).await?;
+                            interaction.create_response(ctx, CreateInteractionResponse::UpdateMessage(CreateInteractionResponseMessage::new()
+                                .embed(event.embed(&pool).await?)
+                            )).await?;
+                            event.update_announcements(&ctx, &pool, Some(interaction.message.id)).await?;
+                        } else {
+                            interaction.create_response(ctx, CreateInteractionResponse::Message(CreateInteractionResponseMessage::new()
+                                .ephemeral(true)
+                                .content("this occurrence of the event has been skipped or moved")
+                            )).await?;
+                        }
+                    } else {
+                        interaction.create_response(ctx, CreateInteractionResponse::Message(CreateInteractionResponseMessage::new()
+                            .ephemeral(true)
+                            .content("this event has been cancelled")
+                        )).await?;
+                    }
+                } else {
+                    // e.g. a button from a feature which has since been removed
+                    interaction.create_response(ctx, CreateInteractionResponse::Message(CreateInteractionResponseMessage::new()
+                        .ephemeral(true)
+                        .content("this button is no longer supported")
+                    )).await?;
+                },
                 _ => {}
             }
             Ok(())
@@ -473,12 +1206,12 @@ async fn main() -> Result<serenity_utils::Builder, Error> {
         .event_handler(serenity_utils::handler::user_list_exporter::<UserListExporter>())
         .event_handler(serenity_utils::handler::voice_state_exporter::<VoiceStateExporter>())
         .data::<Config>(config)
+        .data::<log::LagStats>(HashMap::default())
         .data::<Database>(PgPool::connect_with(PgConnectOptions::default().database("wurstmineberg").application_name("wurstminebot")).await?)
         .task(|ctx_fut, notify_thread_crash| async move {
-            if let Err(e) = cal::notifications(ctx_fut).await {
-                eprintln!("{}", e);
-                notify_thread_crash(format!("calendar notifications"), Box::new(e), None).await;
-            }
+            let Err(e) = cal::notifications(ctx_fut).await;
+            eprintln!("{}", e);
+            notify_thread_crash(format!("calendar notifications"), Box::new(e), None).await;
         })
         .task(|ctx_fut, notify_thread_crash| async move {
             if let Err(e) = http::rocket(ctx_fut).launch().await {
@@ -30,6 +30,11 @@ fn user_to_id(user: &Bound<'_, PyAny>) -> PyResult<UserId> {
     builder.build()
 }
 
+#[pyfunction] fn backfill(world: String) -> PyResult<()> {
+    wurstminebot_ipc::backfill(world)
+        .map_err(|e| CommandError::new_err(e.to_string()))
+}
+
 #[pyfunction] fn channel_msg(channel_id: u64, msg: String) -> PyResult<()> {
     wurstminebot_ipc::channel_msg(ChannelId::new(channel_id), msg)
         .map_err(|e| CommandError::new_err(e.to_string()))
@@ -48,6 +53,7 @@ fn user_to_id(user: &Bound<'_, PyAny>) -> PyResult<UserId> {
 #[pymodule] fn wurstminebot(_: Python<'_>, m: Bound<'_, PyModule>) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(escape, m.clone())?)?;
     //TODO make sure that all IPC commands are listed below
+    m.add_function(wrap_pyfunction!(backfill, m.clone())?)?;
     m.add_function(wrap_pyfunction!(channel_msg, m.clone())?)?;
     m.add_function(wrap_pyfunction!(quit, m.clone())?)?;
     m.add_function(wrap_pyfunction!(set_display_name, m.clone())?)?;
@@ -1,32 +1,71 @@
 use {
-    std::borrow::Cow,
+    std::{
+        borrow::Cow,
+        cmp::Reverse,
+        collections::HashMap,
+        convert::Infallible as Never,
+        io,
+        iter,
+    },
     chrono::{
         Duration,
         prelude::*,
     },
-    serde::Deserialize,
+    chrono_tz::Tz,
+    futures::future,
+    itertools::Itertools as _,
+    lazy_regex::regex_captures,
+    minecraft::chat::Chat,
+    serde::{
+        Deserialize,
+        Serialize,
+    },
     serenity::all::{
+        ButtonStyle,
         Colour,
         Context,
+        CreateActionRow,
+        CreateButton,
         CreateEmbed,
         CreateMessage,
+        CreateScheduledEvent,
+        EditMessage,
+        EditScheduledEvent,
+        HttpError,
+        MessageBuilder,
+        MessageId,
+        ScheduledEventId,
+        ScheduledEventStatus,
+        ScheduledEventType,
     },
     serenity_utils::RwFuture,
     sqlx::{
         PgPool,
+        postgres::PgListener,
         types::Json,
     },
-    tokio::time::sleep,
+    systemd_minecraft::World,
+    tokio::{
+        select,
+        time::sleep,
+    },
     crate::{
+        DEV,
         Database,
         Error,
         GENERAL,
+        WURSTMINEBERG,
+        config::{
+            Config,
+            EventReminder,
+        },
+        minecraft::tellraw_localized,
         people::PersonId,
         util::join,
     },
 };
 
-#[derive(Clone, Deserialize)]
+#[derive(Clone, Deserialize, Serialize)]
 #[serde(tag = "type", rename_all = "camelCase")]
 pub enum EventKind {
     Minigame {
@@ -53,16 +92,292 @@ pub enum EventKind {
     },
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Frequency {
+    Weekly,
+    Monthly,
+}
+
+/// A rule describing how an event repeats, modeled after a subset of iCalendar's `RRULE`.
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Recurrence {
+    pub frequency: Frequency,
+    /// The event occurs every `interval` weeks or months.
+    #[serde(default = "make_one")]
+    pub interval: u32,
+    /// No occurrences start after this time.
+    #[serde(default)]
+    pub until: Option<DateTime<Utc>>,
+    /// The total number of occurrences, including any listed in `exceptions`.
+    #[serde(default)]
+    pub count: Option<u32>,
+    /// Start times of occurrences which have been skipped.
+    #[serde(default)]
+    pub exceptions: Vec<DateTime<Utc>>,
+}
+
+fn make_one() -> u32 { 1 }
+
+/// The largest allowed [`Recurrence::interval`].
+pub const MAX_INTERVAL: u32 = 52;
+
+/// [`Event::occurrences`] ends after this many consecutive repetitions which don't exist, since e.g. a monthly event on the 31st with a large interval may never occur again.
+const MAX_SKIPPED_OCCURRENCES: u32 = 100;
+
+impl Recurrence {
+    /// The start time of the `n`th repetition of an event first starting at `first`, or `None` if there is no such date (e.g. the 31st of a month with 30 days or a date out of range), in which case the repetition is skipped.
+    fn nth(&self, first: DateTime<Utc>, n: u32) -> Option<DateTime<Utc>> {
+        let steps = n.checked_mul(self.interval)?;
+        match self.frequency {
+            Frequency::Weekly => first.checked_add_signed(Duration::try_weeks(steps.into())?),
+            Frequency::Monthly => {
+                let months = first.month0().checked_add(steps)?;
+                let date = NaiveDate::from_ymd_opt(first.year().checked_add(i32::try_from(months / 12).ok()?)?, months % 12 + 1, first.day())?;
+                Some(date.and_time(first.time()).and_utc())
+            }
+        }
+    }
+
+    /// The value of the iCalendar `RRULE` property for this recurrence.
+    pub(crate) fn rrule(&self) -> String {
+        let mut rrule = format!("FREQ={};INTERVAL={}", match self.frequency {
+            Frequency::Weekly => "WEEKLY",
+            Frequency::Monthly => "MONTHLY",
+        }, self.interval);
+        if let Some(until) = self.until {
+            rrule.push_str(&format!(";UNTIL={}", until.format("%Y%m%dT%H%M%SZ")));
+        }
+        if let Some(count) = self.count {
+            rrule.push_str(&format!(";COUNT={count}"));
+        }
+        rrule
+    }
+}
+
+/// A response to an event announcement's RSVP buttons.
+#[derive(Clone, Copy, PartialEq, Eq, sqlx::Type)]
+#[sqlx(type_name = "rsvp", rename_all = "snake_case")]
+pub enum Rsvp {
+    Going,
+    Maybe,
+    NotGoing,
+}
+
+impl Rsvp {
+    const ALL: [Self; 3] = [Self::Going, Self::Maybe, Self::NotGoing];
+
+    fn label(&self) -> &'static str {
+        match self {
+            Self::Going => "Going",
+            Self::Maybe => "Maybe",
+            Self::NotGoing => "Not going",
+        }
+    }
+
+    fn custom_id(&self, event_id: i32) -> String {
+        format!("rsvp_{event_id}_{}", match self {
+            Self::Going => "going",
+            Self::Maybe => "maybe",
+            Self::NotGoing => "not_going",
+        })
+    }
+
+    /// Parses the custom ID of an RSVP button, returning the event ID and the chosen response.
+    pub fn from_custom_id(custom_id: &str) -> Option<(i32, Self)> {
+        let (_, event_id, rsvp) = regex_captures!("^rsvp_([0-9]+)_(going|maybe|not_going)$", custom_id)?;
+        Some((event_id.parse().ok()?, match rsvp {
+            "going" => Self::Going,
+            "maybe" => Self::Maybe,
+            "not_going" => Self::NotGoing,
+            _ => unreachable!("checked by regex"),
+        }))
+    }
+}
+
+impl EventKind {
+    /// The names used for the `type` field in the JSON representation.
+    pub const TAGS: [&'static str; 6] = ["minigame", "renascence", "renascenceDragonFight", "tour", "usc", "other"];
+
+    /// The value of the `type` field in the JSON representation of this event kind.
+    pub fn tag(&self) -> &'static str {
+        match self {
+            Self::Minigame { .. } => "minigame",
+            Self::Renascence { .. } => "renascence",
+            Self::RenascenceDragonFight { .. } => "renascenceDragonFight",
+            Self::Tour { .. } => "tour",
+            Self::Usc { .. } => "usc",
+            Self::Other { .. } => "other",
+        }
+    }
+}
+
 #[derive(Clone)]
-pub(crate) struct Event {
-    pub(crate) id: i32,
-    pub(crate) start_time: DateTime<Utc>,
-    pub(crate) end_time: DateTime<Utc>,
-    pub(crate) kind: Json<EventKind>,
+pub struct Event {
+    pub id: i32,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub kind: Json<EventKind>,
+    pub recurrence: Option<Json<Recurrence>>,
+    /// Cancelled events are kept in the database so calendar clients can be notified of the cancellation.
+    pub cancelled: bool,
+    /// The time of the last change to this event, used as the `DTSTAMP` in the ICS feed.
+    pub modified: DateTime<Utc>,
 }
 
 impl Event {
-    pub(crate) async fn title(&self, pool: &PgPool) -> String {
+    pub async fn get(pool: &PgPool, id: i32) -> sqlx::Result<Option<Self>> {
+        sqlx::query_as!(Self, r#"SELECT id, start_time, end_time, kind as "kind: Json<EventKind>", recurrence as "recurrence: Json<Recurrence>", cancelled, modified FROM calendar WHERE id = $1"#, id).fetch_optional(pool).await
+    }
+
+    /// Returns the current or next occurrence of each event which hasn't ended yet, ordered by start time.
+    pub async fn upcoming(pool: &PgPool) -> sqlx::Result<Vec<Self>> {
+        let mut events = sqlx::query_as!(Self, r#"SELECT id, start_time, end_time, kind as "kind: Json<EventKind>", recurrence as "recurrence: Json<Recurrence>", cancelled, modified FROM calendar WHERE NOT cancelled AND (end_time > $1 OR recurrence IS NOT NULL)"#, Utc::now()).fetch_all(pool).await?
+            .into_iter()
+            .filter_map(|event| event.next_occurrence())
+            .collect_vec();
+        events.sort_by_key(|event| event.start_time);
+        Ok(events)
+    }
+
+    pub async fn add(pool: &PgPool, start_time: DateTime<Utc>, end_time: DateTime<Utc>, kind: EventKind, recurrence: Option<Recurrence>) -> sqlx::Result<Self> {
+        let modified = Utc::now();
+        let id = sqlx::query_scalar!("INSERT INTO calendar (start_time, end_time, kind, recurrence, modified) VALUES ($1, $2, $3, $4, $5) RETURNING id", start_time, end_time, Json(&kind) as _, recurrence.as_ref().map(Json) as _, modified).fetch_one(pool).await?;
+        notify_changed(pool).await?;
+        Ok(Self { id, start_time, end_time, kind: Json(kind), recurrence: recurrence.map(Json), cancelled: false, modified })
+    }
+
+    /// Returns the individual occurrences of this event in chronological order.
+    ///
+    /// For events without a recurrence rule, this is just the event itself. For recurring events, this may be infinite.
+    pub fn occurrences(&self) -> impl Iterator<Item = Self> + '_ {
+        let duration = self.end_time - self.start_time;
+        let mut n = 0u32;
+        let mut count = 0;
+        let mut skipped = 0;
+        iter::from_fn(move || loop {
+            let start_time = match self.recurrence {
+                None => if n == 0 { self.start_time } else { return None },
+                Some(Json(ref recurrence)) => {
+                    if recurrence.count.is_some_and(|max_count| count >= max_count) { return None }
+                    let Some(start_time) = recurrence.nth(self.start_time, n) else {
+                        skipped += 1;
+                        if skipped >= MAX_SKIPPED_OCCURRENCES { return None }
+                        n = n.checked_add(1)?;
+                        continue
+                    };
+                    skipped = 0;
+                    if recurrence.until.is_some_and(|until| start_time > until) { return None }
+                    count += 1;
+                    if recurrence.exceptions.contains(&start_time) {
+                        n = n.checked_add(1)?;
+                        continue
+                    }
+                    start_time
+                }
+            };
+            n = n.checked_add(1)?;
+            return Some(Self { start_time, end_time: start_time.checked_add_signed(duration)?, ..self.clone() })
+        })
+    }
+
+    /// Returns the occurrence of this event starting at the given time, or `None` if there is none, e.g. because it has been skipped.
+    pub fn occurrence(&self, start_time: DateTime<Utc>) -> Option<Self> {
+        self.occurrences()
+            .take_while(|occurrence| occurrence.start_time <= start_time)
+            .find(|occurrence| occurrence.start_time == start_time)
+    }
+
+    /// Returns the occurrence of this event for which the given announcement was posted, if it still exists.
+    pub async fn announced_occurrence(&self, pool: &PgPool, message: MessageId) -> sqlx::Result<Option<Self>> {
+        let Some(start_time) = sqlx::query_scalar!("SELECT start_time FROM calendar_reminders WHERE event = $1 AND message = $2", self.id, i64::from(message)).fetch_optional(pool).await? else { return Ok(None) };
+        Ok(self.occurrence(start_time))
+    }
+
+    /// Writes changes to this event's start time, end time, kind, or recurrence to the database.
+    ///
+    /// For recurring events, this must be called on the event as stored in the database, not on one of its occurrences.
+    pub async fn save(&mut self, pool: &PgPool) -> sqlx::Result<()> {
+        self.modified = Utc::now();
+        sqlx::query!("UPDATE calendar SET start_time = $1, end_time = $2, kind = $3, recurrence = $4, modified = $5 WHERE id = $6", self.start_time, self.end_time, &self.kind as _, &self.recurrence as _, self.modified, self.id).execute(pool).await?;
+        notify_changed(pool).await?;
+        Ok(())
+    }
+
+    pub async fn cancel(&mut self, pool: &PgPool) -> sqlx::Result<()> {
+        self.cancelled = true;
+        self.modified = Utc::now();
+        sqlx::query!("UPDATE calendar SET cancelled = TRUE, modified = $1 WHERE id = $2", self.modified, self.id).execute(pool).await?;
+        notify_changed(pool).await?;
+        Ok(())
+    }
+
+    /// Returns the first occurrence of this event which hasn't ended yet.
+    pub fn next_occurrence(&self) -> Option<Self> {
+        let now = Utc::now();
+        self.occurrences().find(|occurrence| occurrence.end_time > now)
+    }
+
+    /// Records a response for this occurrence of the event.
+    pub async fn set_rsvp(&self, pool: &PgPool, person: &PersonId, rsvp: Rsvp) -> sqlx::Result<()> {
+        sqlx::query!("INSERT INTO calendar_rsvps (event, start_time, person, rsvp) VALUES ($1, $2, $3, $4) ON CONFLICT (event, start_time, person) DO UPDATE SET rsvp = EXCLUDED.rsvp", self.id, self.start_time, Json(person) as _, rsvp as _).execute(pool).await?;
+        Ok(())
+    }
+
+    /// The embed used for announcements of this occurrence of the event, including the responses to the RSVP buttons.
+    pub async fn embed(&self, pool: &PgPool) -> sqlx::Result<CreateEmbed> {
+        let mut e = CreateEmbed::new()
+            .colour(Colour(8794372))
+            .title(self.title(pool).await);
+        if let Some(loc) = self.discord_location() {
+            e = e.description(loc);
+        }
+        e = e.field("starts", format!("<t:{}:F>", self.start_time.timestamp()), false)
+            .field("ends", format!("<t:{}:F>", self.end_time.timestamp()), false);
+        let rsvps = sqlx::query!(r#"SELECT person AS "person: Json<PersonId>", rsvp AS "rsvp: Rsvp" FROM calendar_rsvps WHERE event = $1 AND start_time = $2 ORDER BY person"#, self.id, self.start_time).fetch_all(pool).await?;
+        for rsvp in Rsvp::ALL {
+            let mut names = Vec::default();
+            for row in rsvps.iter().filter(|row| row.rsvp == rsvp) {
+                names.push(row.person.0.display(pool).await);
+            }
+            if let Some(names) = join(names) {
+                e = e.field(rsvp.label(), names, false);
+            }
+        }
+        Ok(e)
+    }
+
+    fn rsvp_buttons(&self) -> Vec<CreateActionRow> {
+        vec![CreateActionRow::Buttons(Rsvp::ALL.into_iter().map(|rsvp| CreateButton::new(rsvp.custom_id(self.id))
+            .label(rsvp.label())
+            .style(match rsvp {
+                Rsvp::Going => ButtonStyle::Success,
+                Rsvp::Maybe => ButtonStyle::Secondary,
+                Rsvp::NotGoing => ButtonStyle::Danger,
+            })
+        ).collect())]
+    }
+
+    /// Refreshes the embeds of all reminders that have been posted for this occurrence of the event.
+    ///
+    /// Announcements which have been deleted are skipped.
+    pub async fn update_announcements(&self, ctx: &Context, pool: &PgPool, skip: Option<MessageId>) -> Result<(), Error> {
+        let messages = sqlx::query_scalar!(r#"SELECT message AS "message!" FROM calendar_reminders WHERE event = $1 AND start_time = $2 AND message IS NOT NULL"#, self.id, self.start_time).fetch_all(pool).await?;
+        let embed = self.embed(pool).await?;
+        for message in messages {
+            let message = MessageId::new(message as u64);
+            if skip.is_some_and(|skip| skip == message) { continue }
+            match GENERAL.edit_message(ctx, message, EditMessage::new().embed(embed.clone())).await {
+                Ok(_) => {}
+                Err(serenity::Error::Http(HttpError::UnsuccessfulRequest(response))) if response.error.code == UNKNOWN_MESSAGE => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn title(&self, pool: &PgPool) -> String {
         match self.kind.0 {
             EventKind::Minigame { ref minigame } => format!("Minigame Night: {}", minigame),
             EventKind::Renascence { ref settlement, .. } => format!("Renascence: {}", settlement),
@@ -100,7 +415,52 @@ impl Event {
         }
     }
 
-    fn discord_location(&self) -> Option<Cow<'static, str>> {
+    /// Additional information about the event for calendar clients.
+    pub(crate) async fn ics_description(&self, pool: &PgPool) -> Option<String> {
+        match self.kind.0 {
+            EventKind::Minigame { .. } => None,
+            EventKind::Renascence { ref settlement, hub_coords: [x, z] } => Some(format!("Founding of the Renascence settlement {settlement}. Its Nether hub is at {x}, {z}.")),
+            EventKind::RenascenceDragonFight { ref settlement } => Some(format!("Dragon fight hosted by the Renascence settlement {settlement}.")),
+            EventKind::Tour { ref guests, .. } => {
+                let mut guest_names = Vec::default();
+                for guest in guests {
+                    guest_names.push(guest.display(pool).await);
+                }
+                Some(format!("Guests: {}", join(guest_names).unwrap_or_else(|| format!("no one"))))
+            }
+            EventKind::Usc { .. } => None,
+            EventKind::Other { .. } => None,
+        }
+    }
+
+    /// A wurstmineberg.de page about the event, using the same links as [`Event::discord_location`]. Kinds without a known page have no URL.
+    pub(crate) fn url(&self) -> Option<Cow<'static, str>> {
+        match self.kind.0 {
+            EventKind::Minigame { .. } => None,
+            EventKind::Renascence { ref settlement, .. } | EventKind::RenascenceDragonFight { ref settlement } => Some(Cow::Owned(format!("https://wurstmineberg.de/renascence#{}", settlement.to_lowercase()))),
+            EventKind::Tour { area: Some(_), .. } => None,
+            EventKind::Tour { area: None, .. } => Some(Cow::Borrowed(if self.start_time >= Utc.with_ymd_and_hms(2019, 4, 7, 0, 0, 0).single().expect("invalid UTC datetime") {
+                "https://wurstmineberg.de/wiki/renascence#zucchini"
+            } else {
+                "https://wurstmineberg.de/wiki/old-spawn#platz-des-ursprungs"
+            })),
+            EventKind::Usc { .. } => None,
+            EventKind::Other { .. } => None,
+        }
+    }
+
+    pub(crate) fn category(&self) -> &'static str {
+        match self.kind.0 {
+            EventKind::Minigame { .. } => "Minigame Night",
+            EventKind::Renascence { .. } => "Renascence",
+            EventKind::RenascenceDragonFight { .. } => "Dragon Fight",
+            EventKind::Tour { .. } => "Server Tour",
+            EventKind::Usc { .. } => "Ultra Softcore",
+            EventKind::Other { .. } => "Other",
+        }
+    }
+
+    pub(crate) fn discord_location(&self) -> Option<Cow<'static, str>> {
         match self.kind.0 {
             EventKind::Minigame { .. } => Some(Cow::Borrowed("minigame.wurstmineberg.de")),
             EventKind::Renascence { hub_coords: [x, z], .. } => Some(Cow::Owned(format!("[Hub](https://wurstmineberg.de/wiki/nether-hub-system) {}, {}\nThe Nether\nWurstmineberg", x, z))),
@@ -118,37 +478,199 @@ impl Event {
     }
 }
 
-pub async fn notifications(ctx_fut: RwFuture<Context>) -> Result<(), Error> {
+/// The Discord JSON error code for a message which doesn't exist (anymore).
+const UNKNOWN_MESSAGE: isize = 10008;
+
+/// The Postgres notification channel on which changes to the `calendar` table are announced.
+///
+/// Anything that modifies the `calendar` table outside of this module should run `NOTIFY calendar` afterwards so that reminders are rescheduled.
+pub const NOTIFY_CHANNEL: &str = "calendar";
+
+async fn notify_changed(pool: &PgPool) -> sqlx::Result<()> {
+    sqlx::query("SELECT pg_notify($1, '')").bind(NOTIFY_CHANNEL).execute(pool).await?;
+    Ok(())
+}
+
+/// Reminders whose time has passed by more than this, e.g. because the bot was offline, are recorded as sent without being posted.
+const REMINDER_GRACE_PERIOD: Duration = Duration::minutes(10);
+
+/// If syncing the guild's scheduled events fails, it's retried after this long even if the calendar hasn't changed.
+const SYNC_RETRY_INTERVAL: Duration = Duration::minutes(5);
+
+async fn post_reminder(ctx: &Context, pool: &PgPool, event: &Event, reminder: &EventReminder, is_final: bool) -> Result<MessageId, Error> {
+    let title = event.title(pool).await;
+    let msg = GENERAL.send_message(ctx, CreateMessage::new()
+        .content(reminder.message.replace("{title}", &title).replace("{start}", &format!("<t:{}:R>", event.start_time.timestamp())))
+        .add_embed(event.embed(pool).await?)
+        .components(event.rsvp_buttons())
+    ).await?;
+    if is_final {
+        let chat = |timezone: Tz| {
+            let mut chat = Chat::from("[Calendar] ");
+            chat.color(minecraft::chat::Color::Aqua);
+            // same wording as on Discord, with the relative start time written out since there are no Discord timestamps in-game
+            for (idx, part) in reminder.message.split("{start}").enumerate() {
+                if idx > 0 {
+                    let mut start = Chat::from(if reminder.minutes_before == 0 {
+                        format!("now")
+                    } else if reminder.minutes_before == 1 {
+                        format!("in 1 minute")
+                    } else {
+                        format!("in {} minutes", reminder.minutes_before)
+                    });
+                    start.on_hover(minecraft::chat::HoverEvent::ShowText(Box::new(Chat::from(event.start_time.with_timezone(&timezone).format("%Y-%m-%d %H:%M:%S %Z").to_string()))));
+                    chat.add_extra(start);
+                }
+                if !part.is_empty() {
+                    chat.add_extra(part.replace("{title}", &title));
+                }
+            }
+            chat
+        };
+        for world in World::all_running().await? {
+            match tellraw_localized(pool, &world, chat).await {
+                Ok(()) => {}
+                Err(Error::Minecraft(systemd_minecraft::Error::Rcon(rcon::Error::Io(e)))) if e.kind() == io::ErrorKind::ConnectionRefused => {} // Minecraft world not fully running yet, skip broadcast
+                Err(e) => return Err(e),
+            }
+        }
+    }
+    Ok(msg.id)
+}
+
+/// Mirrors upcoming calendar events as guild scheduled events in the Wurstmineberg guild.
+///
+/// The IDs of the scheduled events are stored in the `calendar` table, so repeated syncs don't create duplicates. Scheduled events created by the bot which don't belong to an upcoming calendar event (e.g. because it was cancelled) are deleted.
+async fn sync_scheduled_events(ctx: &Context, pool: &PgPool) -> Result<(), Error> {
+    let bot_id = ctx.cache.current_user().id;
+    let mut discord_events = WURSTMINEBERG.scheduled_events(ctx, false).await?
+        .into_iter()
+        .filter(|discord_event| discord_event.creator_id == Some(bot_id))
+        .map(|discord_event| (discord_event.id, discord_event))
+        .collect::<HashMap<_, _>>();
+    let discord_event_ids = sqlx::query!(r#"SELECT id, discord_scheduled_event AS "discord_scheduled_event!" FROM calendar WHERE discord_scheduled_event IS NOT NULL"#).fetch_all(pool).await?
+        .into_iter()
+        .map(|row| (row.id, ScheduledEventId::new(row.discord_scheduled_event as u64)))
+        .collect::<HashMap<_, _>>();
+    let now = Utc::now();
+    for event in Event::upcoming(pool).await? {
+        // Discord limits both of these to 100 characters
+        let name = event.title(pool).await.chars().take(100).collect::<String>();
+        let location = event.discord_location().map_or_else(|| format!("Wurstmineberg"), |loc| loc.chars().take(100).collect());
+        if let Some(discord_event) = discord_event_ids.get(&event.id).and_then(|discord_event_id| discord_events.remove(discord_event_id)) {
+            let same_time = discord_event.start_time.unix_timestamp() == event.start_time.timestamp() && discord_event.end_time.is_some_and(|end_time| end_time.unix_timestamp() == event.end_time.timestamp());
+            if same_time && discord_event.name == name && discord_event.metadata.as_ref().and_then(|metadata| metadata.location.as_deref()) == Some(&*location) { continue }
+            match discord_event.status {
+                ScheduledEventStatus::Scheduled if event.start_time > now => {
+                    WURSTMINEBERG.edit_scheduled_event(ctx, discord_event.id, EditScheduledEvent::new()
+                        .name(name)
+                        .location(location)
+                        .start_time(event.start_time)
+                        .end_time(event.end_time)
+                    ).await?;
+                    continue
+                }
+                ScheduledEventStatus::Active if discord_event.start_time.unix_timestamp() == event.start_time.timestamp() => continue, // already started, can no longer be edited
+                // belongs to a previous occurrence of a recurring event
+                _ => WURSTMINEBERG.delete_scheduled_event(ctx, discord_event.id).await?,
+            }
+        }
+        if event.start_time > now {
+            let discord_event = WURSTMINEBERG.create_scheduled_event(ctx, CreateScheduledEvent::new(ScheduledEventType::External, name, event.start_time)
+                .location(location)
+                .end_time(event.end_time)
+            ).await?;
+            sqlx::query!("UPDATE calendar SET discord_scheduled_event = $1 WHERE id = $2", i64::from(discord_event.id), event.id).execute(pool).await?;
+        }
+    }
+    for discord_event_id in discord_events.into_keys() {
+        WURSTMINEBERG.delete_scheduled_event(ctx, discord_event_id).await?;
+    }
+    Ok(())
+}
+
+/// Posts the reminders configured in `eventReminders` to #general and keeps the guild's scheduled events in sync with the calendar.
+///
+/// Sent reminders are recorded in the `calendar_reminders` table so they aren't repeated after a restart. Reminders which were missed by more than [`REMINDER_GRACE_PERIOD`] are recorded without being posted. If an event is moved, its reminders are sent again for the new start time.
+///
+/// The schedule is recomputed whenever a notification arrives on [`NOTIFY_CHANNEL`], so events which are added, moved, or cancelled while the bot is running are handled correctly.
+pub async fn notifications(ctx_fut: RwFuture<Context>) -> Result<Never, Error> {
     let ctx = ctx_fut.read().await;
-    let mut unnotified = {
+    let (pool, mut reminders) = {
         let data = (*ctx).data.read().await;
-        let pool = data.get::<Database>().expect("missing database connection");
-        let now = Utc::now();
-        sqlx::query_as!(Event, r#"SELECT id, start_time, end_time, kind as "kind: Json<EventKind>" FROM calendar WHERE start_time > $1 ORDER BY start_time"#, now + Duration::minutes(30)).fetch_all(pool).await?
+        (
+            data.get::<Database>().expect("missing database connection").clone(),
+            data.get::<Config>().expect("missing config").wurstminebot.event_reminders.clone(),
+        )
     };
-    while !unnotified.is_empty() {
-        let event = unnotified.remove(0);
-        if let Ok(duration) = (event.start_time - Duration::minutes(30) - Utc::now()).to_std() {
-            sleep(duration).await;
-        }
-        let title = {
-            let data = (*ctx).data.read().await;
-            let pool = data.get::<Database>().expect("missing database connection");
-            event.title(pool).await
-        };
-        GENERAL.send_message(&*ctx, CreateMessage::new()
-            .content(format!("event starting <t:{}:R>", event.start_time.timestamp()))
-            .add_embed({
-                let mut e = CreateEmbed::new()
-                    .colour(Colour(8794372))
-                    .title(title);
-                if let Some(loc) = event.discord_location() {
-                    e = e.description(loc);
+    reminders.sort_by_key(|reminder| Reverse(reminder.minutes_before));
+    let mut listener = PgListener::connect_with(&pool).await?;
+    listener.listen(NOTIFY_CHANNEL).await?;
+    let mut sync_failing = false;
+    loop {
+        let mut next_wake = None::<DateTime<Utc>>;
+        // failing to sync scheduled events shouldn't stop reminders from being posted, so the error is reported and the sync is retried later
+        match sync_scheduled_events(&*ctx, &pool).await {
+            Ok(()) => sync_failing = false,
+            Err(e) => {
+                eprintln!("failed to sync scheduled events: {e} ({e:?})");
+                if !sync_failing { // only notify once until the sync succeeds again
+                    if let Err(e) = DEV.say(&*ctx, MessageBuilder::default()
+                        .push("failed to sync scheduled events: ")
+                        .push_mono_safe(e.to_string())
+                        .build()
+                    ).await {
+                        eprintln!("failed to notify about scheduled event sync error: {e} ({e:?})");
+                    }
                 }
-                e.field("starts", format!("<t:{}:F>", event.start_time.timestamp()), false)
-                    .field("ends", format!("<t:{}:F>", event.end_time.timestamp()), false)
-            })
-        ).await?;
+                sync_failing = true;
+                next_wake = Some(Utc::now() + SYNC_RETRY_INTERVAL);
+            }
+        }
+        let now = Utc::now();
+        for event in Event::upcoming(&pool).await? {
+            let sent = sqlx::query_scalar!("SELECT minutes_before FROM calendar_reminders WHERE event = $1 AND start_time = $2", event.id, event.start_time).fetch_all(&pool).await?;
+            let mut due = None;
+            for (idx, reminder) in reminders.iter().enumerate() {
+                if sent.contains(&i32::from(reminder.minutes_before)) { continue }
+                let reminder_time = event.start_time - Duration::minutes(reminder.minutes_before.into());
+                if reminder_time <= now {
+                    // if multiple reminders are due (e.g. after downtime), only the latest one is posted
+                    due = Some(idx);
+                } else {
+                    next_wake = Some(next_wake.map_or(reminder_time, |next_wake| next_wake.min(reminder_time)));
+                }
+            }
+            // once the event is over, the next occurrence of a recurring event is scheduled and the Discord scheduled event is removed
+            next_wake = Some(next_wake.map_or(event.end_time, |next_wake| next_wake.min(event.end_time)));
+            if let Some(due) = due {
+                // the reminders are recorded as sent before posting, so a crash in between skips a reminder rather than posting it twice
+                let mut transaction = pool.begin().await?;
+                for reminder in &reminders[..due] {
+                    sqlx::query!("INSERT INTO calendar_reminders (event, start_time, minutes_before) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING", event.id, event.start_time, i32::from(reminder.minutes_before)).execute(&mut *transaction).await?;
+                }
+                let claimed = sqlx::query_scalar!("INSERT INTO calendar_reminders (event, start_time, minutes_before) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING RETURNING minutes_before", event.id, event.start_time, i32::from(reminders[due].minutes_before)).fetch_optional(&mut *transaction).await?.is_some();
+                transaction.commit().await?;
+                let reminder_time = event.start_time - Duration::minutes(reminders[due].minutes_before.into());
+                if claimed && reminder_time >= now - REMINDER_GRACE_PERIOD {
+                    let message = post_reminder(&*ctx, &pool, &event, &reminders[due], due == reminders.len() - 1).await?;
+                    sqlx::query!("UPDATE calendar_reminders SET message = $4 WHERE event = $1 AND start_time = $2 AND minutes_before = $3", event.id, event.start_time, i32::from(reminders[due].minutes_before), i64::from(message)).execute(&pool).await?;
+                }
+            }
+        }
+        let wait = async {
+            if let Some(next_wake) = next_wake {
+                if let Ok(duration) = (next_wake - Utc::now()).to_std() {
+                    sleep(duration).await;
+                }
+            } else {
+                future::pending::<()>().await;
+            }
+        };
+        select! {
+            () = wait => {}
+            // a notification means the calendar has changed, and `None` means the connection was lost and notifications may have been missed, so replan in either case
+            res = listener.try_recv() => { res?; }
+        }
     }
-    Ok(())
 }
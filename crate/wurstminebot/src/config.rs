@@ -1,7 +1,10 @@
 use {
-    std::collections::{
-        BTreeMap,
-        BTreeSet,
+    std::{
+        collections::{
+            BTreeMap,
+            BTreeSet,
+        },
+        path::PathBuf,
     },
     serde::Deserialize,
     serenity::{
@@ -22,14 +25,55 @@ pub struct Config {
 #[serde(rename_all = "camelCase")]
 pub struct ConfigWurstminebot {
     pub bot_token: String,
+    #[serde(default = "make_event_reminders")]
+    pub event_reminders: Vec<EventReminder>,
+    /// A lag alert is posted to #dev when a world falls behind by at least this many milliseconds in total within 10 minutes.
+    #[serde(default = "make_lag_alert_threshold_ms")]
+    pub lag_alert_threshold_ms: u64,
+    /// Worlds for which lag alerts are also posted in the world channel.
+    #[serde(default)]
+    pub lag_alert_worlds: BTreeSet<String>,
+    /// The log format of each world which doesn't use the vanilla format, e.g. because it runs Paper or a mod loader.
+    #[serde(default)]
+    pub log_formats: BTreeMap<String, crate::log::LogFormat>,
+    /// If set, feedback from commands run by operators in any world is posted in this channel.
+    #[serde(default)]
+    pub op_feedback_channel: Option<ChannelId>,
+    /// Worlds for which players joining and leaving aren't announced in the world channel.
+    #[serde(default)]
+    pub quiet_worlds: BTreeSet<String>,
     #[serde(default)]
     pub self_assignable_roles: BTreeSet<RoleId>,
+    /// A directory with translation files named `<version>.json` for Minecraft versions whose translations can't be found otherwise, e.g. modded versions. Takes priority over all other sources.
+    #[serde(default)]
+    pub translations_dir: Option<PathBuf>,
     #[serde(default)]
     pub world_channels: BTreeMap<String, ChannelId>,
     #[serde(default)]
     pub world_channel_topics: BTreeMap<String, String>,
 }
 
+/// A reminder posted to #general some time before each calendar event.
+///
+/// The reminder with the smallest `minutes_before` is also broadcast in-game to all running worlds.
+#[derive(Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventReminder {
+    /// Limited to 16 bits (about 45 days) so it fits the `calendar_reminders` table.
+    pub minutes_before: u16,
+    /// The message text. `{title}` is replaced with the event title and `{start}` with a relative timestamp of the event's start time. The in-game broadcast of the final reminder uses the same text.
+    pub message: String,
+}
+
+fn make_event_reminders() -> Vec<EventReminder> {
+    vec![EventReminder {
+        minutes_before: 30,
+        message: format!("event starting {{start}}"),
+    }]
+}
+
+fn make_lag_alert_threshold_ms() -> u64 { 60_000 }
+
 impl Config {
     /// Read `/opt/wurstmineberg/config.json` and return it as a `Config`.
     pub async fn new() -> Result<Config, crate::Error> {
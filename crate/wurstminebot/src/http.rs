@@ -1,47 +1,156 @@
 use {
-    std::io,
+    std::{
+        borrow::Cow,
+        collections::{
+            BTreeMap,
+            HashSet,
+        },
+        io,
+    },
     chrono::prelude::*,
     futures::stream::TryStreamExt as _,
+    itertools::Itertools as _,
     ics::{
+        Alarm,
         ICalendar,
         properties::{
+            Categories,
+            Description,
             DtEnd,
             DtStart,
+            ExDate,
+            LastModified,
             Location,
+            RRule,
+            Status,
             Summary,
+            Trigger,
+            URL,
         },
     },
     rocket::{
+        FromForm,
         Request,
         Rocket,
         State,
+        request::FromParam,
         response::{
             Debug,
             Responder,
+            content::RawJson,
+            status,
         },
     },
     rocket_util::Response,
-    serenity::prelude::*,
+    serde::Serialize,
+    serenity::{
+        model::id::UserId,
+        prelude::*,
+    },
     serenity_utils::RwFuture,
-    sqlx::types::Json,
+    sqlx::{
+        PgPool,
+        types::Json,
+    },
     crate::{
         Database,
         cal::{
             Event,
             EventKind,
+            Recurrence,
         },
+        config::Config,
+        log::LagStats,
+        parse,
+        people::PersonId,
+        sessions::Session,
     },
 };
 
 #[derive(Debug, thiserror::Error)]
 enum Error {
     #[error(transparent)] Io(#[from] io::Error),
+    #[error(transparent)] Json(#[from] serde_json::Error),
     #[error(transparent)] Sql(#[from] sqlx::Error),
+    #[error("invalid value for query parameter {0}")]
+    InvalidQuery(&'static str),
 }
 
 impl<'r> Responder<'r, 'static> for Error {
     fn respond_to(self, request: &'r Request<'_>) -> rocket::response::Result<'static> {
-        Debug(self).respond_to(request)
+        match self {
+            Self::InvalidQuery(_) => status::BadRequest(self.to_string()).respond_to(request),
+            _ => Debug(self).respond_to(request),
+        }
+    }
+}
+
+/// Query parameters for restricting which events are included in a calendar feed.
+#[derive(FromForm)]
+struct CalendarQuery<'r> {
+    /// Comma-separated event kinds, using the same names as the `type` field in the JSON representation.
+    kind: Option<&'r str>,
+    /// Only include events ending after this date or time.
+    from: Option<&'r str>,
+    /// Only include events starting before this date or time.
+    to: Option<&'r str>,
+    /// Only include events the Discord user with this ID has RSVPed to or is a tour guest of.
+    person: Option<u64>,
+}
+
+struct CalendarFilter {
+    kinds: Option<Vec<String>>,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    person: Option<(UserId, HashSet<i32>)>,
+}
+
+fn parse_date_query(s: &str) -> Option<DateTime<Utc>> {
+    parse::datetime(s).or_else(|| Some(NaiveDate::parse_from_str(s, "%Y-%m-%d").ok()?.and_time(NaiveTime::MIN).and_utc()))
+}
+
+impl CalendarQuery<'_> {
+    async fn filter(&self, pool: &PgPool) -> Result<CalendarFilter, Error> {
+        Ok(CalendarFilter {
+            kinds: if let Some(kind) = self.kind {
+                let kinds = kind.split(',').map(str::to_owned).collect_vec();
+                if !kinds.iter().all(|kind| EventKind::TAGS.contains(&&**kind)) { return Err(Error::InvalidQuery("kind")) }
+                Some(kinds)
+            } else {
+                None
+            },
+            from: self.from.map(|from| parse_date_query(from).ok_or(Error::InvalidQuery("from"))).transpose()?,
+            to: self.to.map(|to| parse_date_query(to).ok_or(Error::InvalidQuery("to"))).transpose()?,
+            person: if let Some(user_id) = self.person {
+                if user_id == 0 { return Err(Error::InvalidQuery("person")) }
+                let user_id = UserId::new(user_id);
+                // RSVPs are per occurrence, but the feed contains each recurring event as a whole, so an RSVP to any occurrence includes the entire series
+                let rsvps = sqlx::query_scalar!(r#"SELECT DISTINCT event FROM calendar_rsvps WHERE person = to_jsonb($1::BIGINT) AND rsvp <> 'not_going'"#, i64::from(user_id)).fetch_all(pool).await?;
+                Some((user_id, rsvps.into_iter().collect()))
+            } else {
+                None
+            },
+        })
+    }
+}
+
+impl CalendarFilter {
+    fn matches(&self, event: &Event) -> bool {
+        if let Some(ref kinds) = self.kinds {
+            if !kinds.iter().any(|kind| kind == event.kind.tag()) { return false }
+        }
+        if let Some((user_id, ref rsvps)) = self.person {
+            let is_guest = if let EventKind::Tour { ref guests, .. } = event.kind.0 { guests.contains(&PersonId::Discord(user_id)) } else { false };
+            if !is_guest && !rsvps.contains(&event.id) { return false }
+        }
+        if self.from.is_some() || self.to.is_some() {
+            // for recurring events, check whether any occurrence is in range
+            if !event.occurrences()
+                .take_while(|occurrence| self.to.map_or(true, |to| occurrence.start_time < to))
+                .any(|occurrence| self.from.map_or(true, |from| occurrence.end_time > from))
+            { return false }
+        }
+        true
     }
 }
 
@@ -49,31 +158,146 @@ fn ics_datetime<Tz: TimeZone>(datetime: DateTime<Tz>) -> String {
     format!("{}", datetime.with_timezone(&Utc).format("%Y%m%dT%H%M%SZ"))
 }
 
-#[rocket::get("/api/v3/calendar.ics")]
-async fn calendar(ctx_fut: &State<RwFuture<Context>>) -> Result<Response<ICalendar<'_>>, Error> {
+#[rocket::get("/api/v3/calendar.ics?<query..>")]
+async fn calendar(ctx_fut: &State<RwFuture<Context>>, query: CalendarQuery<'_>) -> Result<Response<ICalendar<'_>>, Error> {
     let mut cal = ICalendar::new("2.0", concat!("wurstmineberg.de/", env!("CARGO_PKG_VERSION")));
     let ctx = ctx_fut.read().await;
     let data = (*ctx).data.read().await;
     let pool = data.get::<Database>().expect("missing database connection");
-    let mut events = sqlx::query_as!(Event, r#"SELECT id, start_time, end_time, kind as "kind: Json<EventKind>" FROM calendar"#).fetch(pool);
+    let reminders = &data.get::<Config>().expect("missing config").wurstminebot.event_reminders;
+    let filter = query.filter(pool).await?;
+    let mut events = sqlx::query_as!(Event, r#"SELECT id, start_time, end_time, kind as "kind: Json<EventKind>", recurrence as "recurrence: Json<Recurrence>", cancelled, modified FROM calendar"#).fetch(pool);
     while let Some(event) = events.try_next().await? {
-        let mut cal_event = ics::Event::new(format!("event{}@wurstmineberg.de", event.id), ics_datetime(Utc::now()));
-        cal_event.push(Summary::new(ics::escape_text(event.title(pool).await)));
+        if !filter.matches(&event) { continue }
+        let mut cal_event = ics::Event::new(format!("event{}@wurstmineberg.de", event.id), ics_datetime(event.modified));
+        cal_event.push(LastModified::new(ics_datetime(event.modified)));
+        let title = event.title(pool).await;
+        cal_event.push(Summary::new(ics::escape_text(title.clone())));
+        if let Some(description) = event.ics_description(pool).await {
+            cal_event.push(Description::new(ics::escape_text(description)));
+        }
         if let Some(loc) = event.ics_location() {
             cal_event.push(Location::new(ics::escape_text(loc)));
         }
+        if let Some(url) = event.url() {
+            cal_event.push(URL::new(url));
+        }
+        cal_event.push(Categories::new(ics::escape_text(event.category())));
+        cal_event.push(Status::new(if event.cancelled { "CANCELLED" } else { "CONFIRMED" }));
         cal_event.push(DtStart::new(ics_datetime(event.start_time)));
         cal_event.push(DtEnd::new(ics_datetime(event.end_time)));
+        if let Some(Json(ref recurrence)) = event.recurrence {
+            cal_event.push(RRule::new(recurrence.rrule()));
+            if !recurrence.exceptions.is_empty() {
+                cal_event.push(ExDate::new(recurrence.exceptions.iter().map(|&exception| ics_datetime(exception)).join(",")));
+            }
+        }
+        if !event.cancelled {
+            for reminder in reminders {
+                cal_event.add_alarm(Alarm::display(
+                    Trigger::new(if reminder.minutes_before == 0 { format!("PT0S") } else { format!("-PT{}M", reminder.minutes_before) }),
+                    Description::new(ics::escape_text(title.clone())),
+                ));
+            }
+        }
         cal.add_event(cal_event);
     }
     Ok(Response(cal))
 }
 
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonEvent {
+    id: i32,
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+    kind: EventKind,
+    recurrence: Option<Recurrence>,
+    cancelled: bool,
+    title: String,
+    ics_location: Option<String>,
+    discord_location: Option<String>,
+}
+
+impl JsonEvent {
+    async fn new(pool: &PgPool, event: Event) -> Self {
+        Self {
+            title: event.title(pool).await,
+            ics_location: event.ics_location().map(Cow::into_owned),
+            discord_location: event.discord_location().map(Cow::into_owned),
+            id: event.id,
+            start_time: event.start_time,
+            end_time: event.end_time,
+            kind: event.kind.0,
+            recurrence: event.recurrence.map(|Json(recurrence)| recurrence),
+            cancelled: event.cancelled,
+        }
+    }
+}
+
+#[rocket::get("/api/v3/calendar.json?<query..>")]
+async fn calendar_json(ctx_fut: &State<RwFuture<Context>>, query: CalendarQuery<'_>) -> Result<RawJson<String>, Error> {
+    let ctx = ctx_fut.read().await;
+    let data = (*ctx).data.read().await;
+    let pool = data.get::<Database>().expect("missing database connection");
+    let filter = query.filter(pool).await?;
+    let mut events = sqlx::query_as!(Event, r#"SELECT id, start_time, end_time, kind as "kind: Json<EventKind>", recurrence as "recurrence: Json<Recurrence>", cancelled, modified FROM calendar ORDER BY start_time"#).fetch(pool);
+    let mut json_events = Vec::default();
+    while let Some(event) = events.try_next().await? {
+        if !filter.matches(&event) { continue }
+        json_events.push(JsonEvent::new(pool, event).await);
+    }
+    Ok(RawJson(serde_json::to_string(&json_events)?))
+}
+
+/// A path segment of the form `<id>.json`.
+struct JsonId(i32);
+
+impl<'a> FromParam<'a> for JsonId {
+    type Error = &'a str;
+
+    fn from_param(param: &'a str) -> Result<Self, &'a str> {
+        param.strip_suffix(".json").and_then(|id| id.parse().ok()).map(Self).ok_or(param)
+    }
+}
+
+#[rocket::get("/api/v3/calendar/<id>")]
+async fn calendar_event_json(ctx_fut: &State<RwFuture<Context>>, id: JsonId) -> Result<Option<RawJson<String>>, Error> {
+    let ctx = ctx_fut.read().await;
+    let data = (*ctx).data.read().await;
+    let pool = data.get::<Database>().expect("missing database connection");
+    Ok(if let Some(event) = Event::get(pool, id.0).await? {
+        Some(RawJson(serde_json::to_string(&JsonEvent::new(pool, event).await)?))
+    } else {
+        None
+    })
+}
+
+#[rocket::get("/api/v3/sessions.json?<world>")]
+async fn sessions_json(ctx_fut: &State<RwFuture<Context>>, world: Option<&str>) -> Result<RawJson<String>, Error> {
+    let ctx = ctx_fut.read().await;
+    let data = (*ctx).data.read().await;
+    let pool = data.get::<Database>().expect("missing database connection");
+    Ok(RawJson(serde_json::to_string(&Session::all(pool, world).await?)?))
+}
+
+#[rocket::get("/api/v3/lag.json")]
+async fn lag_json(ctx_fut: &State<RwFuture<Context>>) -> Result<RawJson<String>, Error> {
+    let ctx = ctx_fut.read().await;
+    let data = (*ctx).data.read().await;
+    let now = Utc::now();
+    let mut summaries = BTreeMap::default();
+    for (world, lag) in data.get::<LagStats>().expect("missing lag stats") {
+        summaries.insert(world, lag.read().await.summary(now));
+    }
+    Ok(RawJson(serde_json::to_string(&summaries)?))
+}
+
 pub fn rocket(ctx_fut: RwFuture<Context>) -> Rocket<rocket::Build> {
     rocket::custom(rocket::Config {
         port: 24810,
         ..rocket::Config::default()
     })
     .manage(ctx_fut)
-    .mount("/", rocket::routes![calendar])
+    .mount("/", rocket::routes![calendar, calendar_event_json, calendar_json, lag_json, sessions_json])
 }
@@ -3,8 +3,15 @@ use {
         Context,
         EditMember,
         HttpError,
+        MessageBuilder,
+    },
+    systemd_minecraft::World,
+    crate::{
+        DEV,
+        Database,
+        WURSTMINEBERG,
+        log,
     },
-    crate::WURSTMINEBERG,
 };
 
 serenity_utils::ipc! {
@@ -15,6 +22,49 @@ serenity_utils::ipc! {
 
     const PORT: u16 = 18809;
 
+    /// Imports chat messages, deaths, and advancements from the entire log history of the given world into the database.
+    ///
+    /// Invoked as `wurstminebot backfill <world>`. Like all IPC commands, this only waits for the bot to accept the command, and reading a world's entire log history can take much longer than that, so the import runs in the background and its result is posted to #dev. Lines which have already been imported are skipped.
+    async fn backfill(ctx: &Context, world: String) -> Result<(), String> {
+        let worlds = World::all().await.map_err(|e| format!("failed to list worlds: {}", e))?;
+        let Some(world) = worlds.into_iter().find(|iter_world| iter_world.to_string() == world) else { return Err(format!("no such world: {}", world)) };
+        let ctx = ctx.clone();
+        tokio::spawn(async move {
+            let (pool, translations_dir, format) = {
+                let data = ctx.data.read().await;
+                let config = data.get::<crate::config::Config>().expect("missing config");
+                (
+                    data.get::<Database>().expect("missing database connection").clone(),
+                    config.wurstminebot.translations_dir.clone(),
+                    config.wurstminebot.log_formats.get(&world.to_string()).copied().unwrap_or_default(),
+                )
+            };
+            let msg = match log::backfill(&pool, translations_dir, format, &world).await {
+                Ok(stats) => {
+                    let mut msg = MessageBuilder::default();
+                    msg.push("backfill for world ");
+                    msg.push_safe(world.to_string());
+                    msg.push(format!(" done: imported {} chat messages, {} deaths, and {} advancements", stats.chat, stats.deaths, stats.advancements));
+                    if !stats.missing_translations.is_empty() {
+                        msg.push(". Deaths and advancements were skipped for the following Minecraft versions since their translations couldn't be loaded: ");
+                        msg.push_safe(stats.missing_translations.join(", "));
+                    }
+                    msg.build()
+                }
+                Err(e) => MessageBuilder::default()
+                    .push("backfill for world ")
+                    .push_safe(world.to_string())
+                    .push(" failed: ")
+                    .push_mono_safe(format!("{e} ({e:?})"))
+                    .build(),
+            };
+            if let Err(e) = DEV.say(&ctx, msg).await {
+                eprintln!("failed to post backfill result: {e}");
+            }
+        });
+        Ok(())
+    }
+
     /// Sends the given message, unescaped, to the given channel.
     async fn channel_msg(ctx: &Context, channel: ChannelId, msg: String) -> Result<(), String> {
         channel.say(ctx, msg).await.map_err(|e| format!("failed to send channel message: {}", e))?;
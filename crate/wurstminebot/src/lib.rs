@@ -21,6 +21,9 @@ pub mod log;
 pub mod minecraft;
 pub mod parse;
 pub mod people;
+pub mod sessions;
+pub mod stats;
+pub mod translations;
 pub mod twitch;
 mod util;
 
@@ -55,6 +58,8 @@ pub enum Error {
     #[error(transparent)] TwitchValidate(#[from] twitch_irc::validate::Error),
     #[error("{0}: {1}")]
     Annotated(String, Box<Error>),
+    #[error("unexpected output from list command: {0:?}")]
+    MalformedPlayerList(String),
     #[error("IRC channel name \"{0}\" doesn't start with \"#\"")]
     MalformedTwitchChannelName(String),
     #[error("encountered user without join date")]
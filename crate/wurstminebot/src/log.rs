@@ -1,16 +1,31 @@
 use {
     std::{
-        collections::HashMap,
+        cmp::Reverse,
+        collections::{
+            HashMap,
+            HashSet,
+            VecDeque,
+            hash_map,
+        },
         convert::Infallible as Never,
-        path::PathBuf,
+        mem,
+        path::{
+            Path,
+            PathBuf,
+        },
         pin::pin,
         str::FromStr,
         sync::Arc,
-        time::Duration,
+        time::{
+            Duration,
+            Instant,
+        },
+    },
+    chrono::{
+        TimeDelta,
+        prelude::*,
     },
-    chase::Chaser,
     futures::{
-        future::try_join_all,
         pin_mut,
         prelude::*,
         stream::{
@@ -24,9 +39,16 @@ use {
         regex_replace_all,
     },
     regex::Regex,
-    serde::Deserialize,
+    serde::{
+        Deserialize,
+        Serialize,
+    },
     serenity::{
         all::{
+            Colour,
+            CreateEmbed,
+            CreateEmbedFooter,
+            CreateMessage,
             EditChannel,
             ExecuteWebhook,
         },
@@ -34,58 +56,61 @@ use {
         utils::MessageBuilder,
     },
     serenity_utils::RwFuture,
+    sqlx::PgPool,
     systemd_minecraft::World,
     tokio::{
         io::{
             self,
-            AsyncBufReadExt as _,
             AsyncReadExt as _,
             BufReader,
         },
-        sync::RwLock,
-    },
-    tokio_stream::wrappers::{
-        LinesStream,
-        ReceiverStream,
+        select,
+        sync::{
+            RwLock,
+            watch,
+        },
+        task::{
+            AbortHandle,
+            JoinSet,
+        },
+        time::{
+            MissedTickBehavior,
+            interval,
+            sleep,
+        },
     },
-    tokio_util::io::StreamReader,
     url::Url,
     wheel::{
         fs::{
             self,
             File,
         },
-        io_error_from_reqwest,
-        traits::{
-            IoResultExt as _,
-            ReqwestResponseExt as _,
-        },
+        traits::IoResultExt as _,
+    },
+    crate::{
+        DEV,
+        Database,
+        minecraft,
+        sessions,
+        translations,
     },
 };
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
-    #[error(transparent)] Chase(#[from] chase::Error),
     #[error(transparent)] Io(#[from] io::Error),
     #[error(transparent)] Json(#[from] serde_json::Error),
     #[error(transparent)] Minecraft(#[from] systemd_minecraft::Error),
     #[error(transparent)] Regex(#[from] regex::Error),
     #[error(transparent)] Reqwest(#[from] reqwest::Error),
     #[error(transparent)] Serenity(#[from] serenity::Error),
+    #[error(transparent)] Sql(#[from] sqlx::Error),
     #[error(transparent)] Task(#[from] tokio::task::JoinError),
+    #[error(transparent)] Translations(#[from] translations::Error),
     #[error(transparent)] Wheel(#[from] wheel::Error),
-    #[error(transparent)] Zip(#[from] async_zip::error::ZipError),
-    /// The `futures::sync::mpsc::Receiver` returned by the `chase` crate yielded an error.
-    #[error("unknown error in log handler")]
-    Channel,
+    #[error(transparent)] Wurstminebot(#[from] Box<crate::Error>),
     #[error("log handler returned unexpectedly")]
     FollowEnded,
-    #[error("no en_us language file in Minecraft client jar")]
-    MissingLangFile,
-    #[error("Minecraft version not found in launcher manifest")]
-    MissingVersion,
-    #[error("failed to start log handler: no worlds configured")]
-    NoWorlds, //TODO remove once `handle` automatically handles new worlds as they are created
 }
 
 impl From<Never> for Error {
@@ -94,7 +119,7 @@ impl From<Never> for Error {
     }
 }
 
-enum Thread {
+pub enum Thread {
     Server,
     Unknown,
 }
@@ -110,7 +135,7 @@ impl FromStr for Thread {
     }
 }
 
-enum Level {
+pub enum Level {
     Info,
     Warn,
     Error,
@@ -122,106 +147,237 @@ impl FromStr for Level {
     fn from_str(s: &str) -> Result<Level, ()> {
         match s {
             "INFO" => Ok(Level::Info),
-            "WARN" => Ok(Level::Warn),
-            "ERROR" => Ok(Level::Error),
+            "WARN" | "WARNING" => Ok(Level::Warn),
+            "ERROR" | "SEVERE" => Ok(Level::Error),
             _ => Err(()),
         }
     }
 }
 
-enum AdvancementKind {
+pub enum AdvancementKind {
     Challenge,
     Goal,
     Task,
 }
 
-enum RegularLine {
+impl AdvancementKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Challenge => "challenge",
+            Self::Goal => "goal",
+            Self::Task => "task",
+        }
+    }
+}
+
+pub enum RegularLine {
     ServerStart {
         minecraft_version: String,
+        /// Set if the translations for this version couldn't be loaded, in which case deaths and advancements aren't recognized until the next server start.
+        translations_error: Option<Error>,
     },
     Chat {
         sender: String,
         msg: String,
         is_action: bool,
     },
+    /// A broadcast from the `/say` command.
+    Say {
+        /// `Server` for the console, `Rcon` for RCON, or the name of the player.
+        sender: String,
+        msg: String,
+    },
+    /// Feedback from a command run by an operator, RCON, or a command block, which is broadcast to the other operators.
+    CommandFeedback {
+        sender: String,
+        msg: String,
+    },
     Advancement {
         kind: AdvancementKind,
         player: String,
         advancement: String,
+        /// The advancements from the language file which have this title. Usually there's exactly one, but titles aren't unique (e.g. some are reused by different tabs), and there are none if the advancement is from a data pack.
+        candidates: Vec<AdvancementInfo>,
     },
     Death {
         msg: String,
+        /// The translation key of the death message which matched, e.g. `death.attack.drown`.
+        key: String,
+        victim: String,
+        /// The player or mob named in the death message, if any.
+        killer: Option<String>,
+    },
+    Done {
+        startup_time: String,
+    },
+    Stopping,
+    Lag {
+        ms_behind: u64,
+        ticks_behind: u64,
+    },
+    Join {
+        player: String,
+    },
+    Leave {
+        player: String,
+    },
+    Unknown {
+        msg: String,
     },
-    Unknown,
 }
 
-struct FollowerState {
-    http_client: reqwest::Client,
-    minecraft_version: Option<String>,
-    death_messages: HashMap<String, Regex>,
+/// The length of the sliding window over which lag warnings are aggregated.
+const LAG_WINDOW: TimeDelta = TimeDelta::minutes(10);
+
+/// The "Can't keep up!" warnings logged by a world within the last [`LAG_WINDOW`].
+#[derive(Default)]
+pub struct Lag {
+    warnings: VecDeque<(DateTime<Utc>, u64, u64)>,
+    alerted: bool,
 }
 
-fn format_to_regex(format: &str) -> Result<Regex, regex::Error> {
-    Regex::new(&format!("^{}$", regex_replace_all!("%[0-9]+\\$s", format, ".*")))
+/// Aggregated numbers about the lag of a world within the last 10 minutes.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LagSummary {
+    pub warnings: usize,
+    pub ms_behind: u64,
+    pub ticks_behind: u64,
+    pub max_ms_behind: u64,
 }
 
-impl RegularLine {
-    async fn parse(state: Arc<RwLock<FollowerState>>, s: &str) -> Result<Self, Error> {
-        Ok(if let Some((_, version)) = regex_captures!("^Starting minecraft server version (.+)$", s) {
-            let mut state = state.write().await;
-            if state.minecraft_version.as_ref().map_or(true, |prev_version| prev_version != version) {
-                state.minecraft_version = Some(version.to_owned());
-                let client_jar_dir = PathBuf::from(format!("/opt/wurstmineberg/home/.minecraft-wurstmineberg/versions/{version}"));
-                let client_jar_path = client_jar_dir.join(format!("{version}.jar"));
-                if !fs::exists(&client_jar_path).await? {
-                    #[derive(Deserialize)]
-                    struct VersionManifestInfo {
-                        id: String,
-                        url: Url,
-                    }
+impl Lag {
+    fn record(&mut self, timestamp: DateTime<Utc>, ms_behind: u64, ticks_behind: u64) {
+        self.warnings.push_back((timestamp, ms_behind, ticks_behind));
+        while self.warnings.front().is_some_and(|&(warning_timestamp, _, _)| warning_timestamp <= timestamp - LAG_WINDOW) {
+            self.warnings.pop_front();
+        }
+    }
 
-                    #[derive(Deserialize)]
-                    struct VersionManifest {
-                        versions: Vec<VersionManifestInfo>,
-                    }
+    pub fn summary(&self, now: DateTime<Utc>) -> LagSummary {
+        let warnings = self.warnings.iter().filter(|&&(timestamp, _, _)| timestamp > now - LAG_WINDOW);
+        LagSummary {
+            warnings: warnings.clone().count(),
+            ms_behind: warnings.clone().map(|&(_, ms_behind, _)| ms_behind).sum(),
+            ticks_behind: warnings.clone().map(|&(_, _, ticks_behind)| ticks_behind).sum(),
+            max_ms_behind: warnings.map(|&(_, ms_behind, _)| ms_behind).max().unwrap_or_default(),
+        }
+    }
 
-                    #[derive(Deserialize)]
-                    struct VersionInfo {
-                        downloads: VersionInfoDownloads,
-                    }
+    /// Returns a summary if the lag has just crossed the given threshold. The alert is re-armed once the lag drops below half the threshold.
+    fn check_alert(&mut self, now: DateTime<Utc>, threshold_ms: u64) -> Option<LagSummary> {
+        let summary = self.summary(now);
+        if self.alerted {
+            if summary.ms_behind < threshold_ms / 2 { self.alerted = false }
+            None
+        } else if summary.ms_behind >= threshold_ms {
+            self.alerted = true;
+            Some(summary)
+        } else {
+            None
+        }
+    }
+}
 
-                    #[derive(Deserialize)]
-                    struct VersionInfoDownloads {
-                        client: VersionInfoDownload,
-                    }
+/// `typemap` key for the recent lag of each world, keyed by world name.
+///
+/// Each world's log handler keeps its own handle, so recording a warning only locks that world's entry.
+pub struct LagStats;
 
-                    #[derive(Deserialize)]
-                    struct VersionInfoDownload {
-                        url: Url,
-                    }
+impl TypeMapKey for LagStats {
+    type Value = HashMap<String, Arc<RwLock<Lag>>>;
+}
 
-                    fs::create_dir_all(&client_jar_dir).await?;
-                    let version_manifest = state.http_client.get("https://launchermeta.mojang.com/mc/game/version_manifest.json")
-                        .send().await?
-                        .detailed_error_for_status().await?
-                        .json_with_text_in_error::<VersionManifest>().await?;
-                    let version_info = state.http_client.get(version_manifest.versions.into_iter().find(|iter_version| iter_version.id == version).ok_or(Error::MissingVersion)?.url)
-                        .send().await?
-                        .detailed_error_for_status().await?
-                        .json_with_text_in_error::<VersionInfo>().await?;
-                    io::copy_buf(&mut StreamReader::new(state.http_client.get(version_info.downloads.client.url).send().await?.detailed_error_for_status().await?.bytes_stream().map_err(io_error_from_reqwest)), &mut File::create(&client_jar_path).await?).await?;
-                }
-                let zip_file = async_zip::tokio::read::fs::ZipFileReader::new(client_jar_path).await?;
-                let index = zip_file.file().entries().iter().position(|entry| entry.filename().as_str().map_or(false, |filename| filename == "assets/minecraft/lang/en_us.json")).ok_or(Error::MissingLangFile)?;
-                let mut english = String::default();
-                zip_file.reader_with_entry(index).await?.read_to_string_checked(&mut english).await?;
-                state.death_messages = serde_json::from_str::<HashMap<String, String>>(&english)?
-                    .into_iter()
-                    .filter(|(key, _)| key.starts_with("death."))
-                    .map(|(key, format)| Ok::<_, Error>((key.to_owned(), format_to_regex(&format)?)))
-                    .try_collect()?;
+/// An advancement from the language file of the current Minecraft version.
+#[derive(Clone)]
+pub struct AdvancementInfo {
+    /// The translation key prefix of the advancement, e.g. `advancements.story.mine_stone`.
+    pub key: String,
+    pub description: String,
+}
+
+struct FollowerState {
+    translation_sources: Vec<translations::Source>,
+    minecraft_version: Option<String>,
+    death_messages: HashMap<String, DeathMessage>,
+    /// Keyed by English title. The log only includes the title, so all advancements with the same title are kept.
+    advancements: HashMap<String, Vec<AdvancementInfo>>,
+}
+
+impl FollowerState {
+    fn new(translation_sources: Vec<translations::Source>) -> Self {
+        Self {
+            minecraft_version: None,
+            death_messages: HashMap::default(),
+            advancements: HashMap::default(),
+            translation_sources,
+        }
+    }
+
+    /// Loads the translations for the given Minecraft version, unless they're already loaded.
+    ///
+    /// If this fails, no deaths or advancements are recognized until the translations for another version are loaded.
+    async fn set_version(&mut self, version: &str) -> Result<(), Error> {
+        if self.minecraft_version.as_ref().map_or(true, |prev_version| prev_version != version) {
+            self.minecraft_version = Some(version.to_owned());
+            self.death_messages.clear();
+            self.advancements.clear();
+            let english = translations::load(&self.translation_sources, version).await?;
+            self.death_messages = english.iter()
+                .filter(|(key, _)| key.starts_with("death."))
+                .map(|(key, format)| Ok::<_, Error>((key.to_owned(), DeathMessage::new(format)?)))
+                .try_collect()?;
+            for (key, title) in &english {
+                let Some(key) = key.strip_prefix("advancements.").and_then(|key| key.strip_suffix(".title")) else { continue };
+                let Some(description) = english.get(&format!("advancements.{key}.description")) else { continue };
+                self.advancements.entry(title.clone()).or_default().push(AdvancementInfo {
+                    description: description.clone(),
+                    key: format!("advancements.{key}"),
+                });
             }
+            for candidates in self.advancements.values_mut() {
+                candidates.sort_unstable_by(|info1, info2| info1.key.cmp(&info2.key));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Death messages whose second placeholder isn't the player or mob who caused the death but the text of a link to [MCPE-28723](https://bugs.mojang.com/browse/MCPE-28723).
+const NON_KILLER_DEATH_MESSAGES: [&str; 2] = [
+    "death.attack.badRespawnPoint.message",
+    "death.attack.netherBed.message",
+];
+
+/// A death message format from the language file.
+struct DeathMessage {
+    regex: Regex,
+    /// The number of characters in the format outside of placeholders.
+    literal_len: usize,
+    placeholders: usize,
+}
+
+impl DeathMessage {
+    fn new(format: &str) -> Result<Self, regex::Error> {
+        Ok(Self {
+            regex: format_to_regex(format)?,
+            literal_len: regex_replace_all!("%([0-9]+)\\$s", format, "").chars().count(),
+            placeholders: lazy_regex::regex!("%([0-9]+)\\$s").find_iter(format).count(),
+        })
+    }
+}
+
+/// Converts a format string from the language file to a regex. Placeholders become capture groups named after their index, e.g. `%2$s` becomes the group `p2`.
+fn format_to_regex(format: &str) -> Result<Regex, regex::Error> {
+    Regex::new(&format!("^{}$", regex_replace_all!("%([0-9]+)\\$s", format, |_, index| format!("(?P<p{index}>.*)"))))
+}
+
+impl RegularLine {
+    /// Parses the content of a log line. The thread and level are used to tell broadcasts from other output that looks similar, e.g. from mods.
+    async fn parse(state: Arc<RwLock<FollowerState>>, thread: &Thread, level: &Level, s: &str) -> Result<Self, Error> {
+        Ok(if let Some((_, version)) = regex_captures!("^Starting minecraft server version (.+)$", s) {
             Self::ServerStart {
+                translations_error: state.write().await.set_version(version).await.err(),
                 minecraft_version: version.to_owned(),
             }
         } else if let Some((_, sender, msg)) = regex_captures!("^(?:\\[Not Secure\\] )?<([A-Za-z0-9_]{3,16})> (.+)$", s) {
@@ -236,163 +392,740 @@ impl RegularLine {
                 msg: msg.to_owned(),
                 is_action: true,
             }
+        } else if let (Thread::Server, Level::Info, Some((_, sender, msg))) = (thread, level, regex_captures!("^(?:\\[Not Secure\\] )?\\[(Server|Rcon|[A-Za-z0-9_]{3,16})\\] (.+)$", s)) {
+            Self::Say {
+                sender: sender.to_owned(),
+                msg: msg.to_owned(),
+            }
+        } else if let (Thread::Server, Level::Info, Some((_, sender, msg))) = (thread, level, regex_captures!("^\\[([^]:]+): (.+)\\]$", s)) {
+            Self::CommandFeedback {
+                sender: sender.to_owned(),
+                msg: msg.to_owned(),
+            }
         } else if let Some((_, player, advancement)) = regex_captures!(r"^([A-Za-z0-9_]{3,16}) has completed the challenge \[(.+)\]$", s) {
             Self::Advancement {
                 kind: AdvancementKind::Challenge,
                 player: player.to_owned(),
                 advancement: advancement.to_owned(),
+                candidates: state.read().await.advancements.get(advancement).cloned().unwrap_or_default(),
             }
         } else if let Some((_, player, advancement)) = regex_captures!(r"^([A-Za-z0-9_]{3,16}) has reached the goal \[(.+)\]$", s) {
             Self::Advancement {
                 kind: AdvancementKind::Goal,
                 player: player.to_owned(),
                 advancement: advancement.to_owned(),
+                candidates: state.read().await.advancements.get(advancement).cloned().unwrap_or_default(),
             }
         } else if let Some((_, player, advancement)) = regex_captures!(r"^([A-Za-z0-9_]{3,16}) has made the advancement \[(.+)\]$", s) {
             Self::Advancement {
                 kind: AdvancementKind::Task,
                 player: player.to_owned(),
                 advancement: advancement.to_owned(),
+                candidates: state.read().await.advancements.get(advancement).cloned().unwrap_or_default(),
             }
-        } else if state.read().await.death_messages.iter().any(|(_, regex)| regex.is_match(s)) {
+        } else if let Some((_, startup_time)) = regex_captures!(r#"^Done \(([0-9.]+s)\)! For help, type "#, s) {
+            Self::Done {
+                startup_time: startup_time.to_owned(),
+            }
+        } else if s == "Stopping server" {
+            Self::Stopping
+        } else if let Some((_, ms_behind, ticks_behind)) = regex_captures!(r"^Can't keep up! Is the server overloaded\? Running ([0-9]+)ms or ([0-9]+) ticks behind$", s) {
+            Self::Lag {
+                ms_behind: ms_behind.parse().expect("number that matches regex should parse"),
+                ticks_behind: ticks_behind.parse().expect("number that matches regex should parse"),
+            }
+        } else if let Some((_, player)) = regex_captures!("^([A-Za-z0-9_]{3,16}) joined the game$", s) {
+            Self::Join {
+                player: player.to_owned(),
+            }
+        } else if let Some((_, player)) = regex_captures!("^([A-Za-z0-9_]{3,16}) left the game$", s) {
+            Self::Leave {
+                player: player.to_owned(),
+            }
+        } else if let Some((key, captures)) = state.read().await.death_messages.iter()
+            .filter_map(|(key, death_message)| Some((key, death_message.regex.captures(s)?, death_message)))
+            // placeholders match anything, so e.g. "X was shot by Y using Z" also matches the format for "X was shot by Y", and "X was killed by magic" matches "X was killed by Y".
+            // Prefer the format with the most literal text, then the one with the fewest placeholders, then the first key so the choice doesn't depend on iteration order.
+            .min_by_key(|(key, _, death_message)| (Reverse(death_message.literal_len), death_message.placeholders, *key))
+            .map(|(key, captures, _)| (key.clone(), captures))
+        {
             Self::Death {
                 msg: s.to_owned(),
+                victim: captures.name("p1").map_or_else(String::default, |victim| victim.as_str().to_owned()),
+                killer: if NON_KILLER_DEATH_MESSAGES.contains(&&*key) { None } else { captures.name("p2").map(|killer| killer.as_str().to_owned()) },
+                key,
             }
         } else {
-            Self::Unknown
+            Self::Unknown {
+                msg: s.to_owned(),
+            }
         })
     }
 }
 
-enum Line {
+/// A line from a Minecraft server log.
+pub enum Line {
     Regular {
-        //timestamp: DateTime<Utc>,
-        //thread: Thread,
-        //level: Level,
+        timestamp: DateTime<Utc>,
+        thread: Thread,
+        level: Level,
         content: RegularLine,
     },
     Unknown,
 }
 
-impl Line {
-    async fn parse(state: Arc<RwLock<FollowerState>>, s: &str) -> Result<Self, Error> {
-        Ok(if let Some((_, _ /*timestamp*/, _ /*thread*/, _ /*level*/, content)) = regex_captures!("^([0-9]+-[0-9]{2}-[0-9]{2} [0-9]{2}:[0-9]{2}:[0-9]{2}) \\[([^]]+)/(INFO|WARN|ERROR)\\]: (.+)$", s) {
-            Self::Regular {
-                //timestamp: Utc.datetime_from_str(timestamp, "%Y-%m-%d %H:%M:%S").ok()?,
-                //thread: thread.parse().never_unwrap(),
-                //level: level.parse().expect("level that matches regex should parse"),
-                content: RegularLine::parse(state, content).await?,
-            }
+/// Converts a timestamp from a log line, which is in the server's local time, to UTC.
+fn local_timestamp(timestamp: NaiveDateTime) -> DateTime<Utc> {
+    Local.from_local_datetime(&timestamp).earliest().map_or_else(|| timestamp.and_utc(), |timestamp| timestamp.with_timezone(&Utc))
+}
+
+/// Converts a time of day from a log line to a timestamp.
+///
+/// `date` is the date of the lines in the log file, see [`read_log`]. If it is `None`, the line is assumed to be from the current log file and to have been logged within the last day.
+fn time_of_day_timestamp(date: Option<NaiveDate>, time: NaiveTime) -> DateTime<Utc> {
+    if let Some(date) = date {
+        local_timestamp(date.and_time(time))
+    } else {
+        let timestamp = local_timestamp(Local::now().date_naive().and_time(time));
+        // a line logged just before midnight may be parsed just after
+        if timestamp > Utc::now() + TimeDelta::hours(1) { timestamp - TimeDelta::days(1) } else { timestamp }
+    }
+}
+
+/// The format of a world's log, which depends on the server software. Configured per world as `logFormats`.
+///
+/// The formats of modded servers only differ in the prefix of each line, so the vanilla formats are also accepted for all of them, e.g. for logs from before a world switched to modded.
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LogFormat {
+    /// `[HH:MM:SS] [thread/LEVEL]: message`, as well as the formats of older versions.
+    #[default]
+    Vanilla,
+    /// `[HH:MM:SS LEVEL]: message`, which doesn't include the thread name.
+    Paper,
+    /// `[HH:MM:SS] [thread/LEVEL] (logger) message`
+    Fabric,
+    /// `[HH:MM:SS] [thread/LEVEL] [logger/marker]: message`, or with the date included as in `[31Jan2024 12:34:56.789]`.
+    Forge,
+}
+
+impl LogFormat {
+    /// Splits a log line into its timestamp, thread name, level, and content.
+    fn split(self, date: Option<NaiveDate>, s: &str) -> Option<(DateTime<Utc>, &str, &str, &str)> {
+        match self {
+            Self::Vanilla => {}
+            Self::Paper => if let Some((_, time, level, content)) = regex_captures!("^\\[([0-9]{2}:[0-9]{2}:[0-9]{2}) (INFO|WARN|ERROR)\\]: (.+)$", s) {
+                let time = NaiveTime::parse_from_str(time, "%H:%M:%S").ok()?;
+                // Paper doesn't log thread names, but most lines are from the server thread, so they're treated as such (e.g. to forward warnings)
+                return Some((time_of_day_timestamp(date, time), "Server thread", level, content))
+            },
+            Self::Fabric => if let Some((_, time, thread, level, _, content)) = regex_captures!("^\\[([0-9]{2}:[0-9]{2}:[0-9]{2})\\] \\[([^]]+)/(INFO|WARN|ERROR)\\] \\(([^)]+)\\) (.+)$", s) {
+                let time = NaiveTime::parse_from_str(time, "%H:%M:%S").ok()?;
+                return Some((time_of_day_timestamp(date, time), thread, level, content))
+            },
+            Self::Forge => if let Some((_, time, thread, level, _, content)) = regex_captures!("^\\[([0-9]{2}:[0-9]{2}:[0-9]{2}|[0-9]{2}[A-Za-z]{3}[0-9]{4} [0-9]{2}:[0-9]{2}:[0-9]{2}\\.[0-9]{3})\\] \\[([^]]+)/(INFO|WARN|ERROR)\\] \\[([^]]*)\\]: (.+)$", s) {
+                let timestamp = if let Ok(timestamp) = NaiveDateTime::parse_from_str(time, "%d%b%Y %H:%M:%S%.3f") {
+                    local_timestamp(timestamp)
+                } else {
+                    time_of_day_timestamp(date, NaiveTime::parse_from_str(time, "%H:%M:%S").ok()?)
+                };
+                return Some((timestamp, thread, level, content))
+            },
+        }
+        if let Some((_, time, thread, level, content)) = regex_captures!("^\\[([0-9]{2}:[0-9]{2}:[0-9]{2})\\] \\[([^]]+)/(INFO|WARN|ERROR)\\]: (.+)$", s) {
+            let time = NaiveTime::parse_from_str(time, "%H:%M:%S").ok()?;
+            Some((time_of_day_timestamp(date, time), thread, level, content))
+        } else if let Some((_, timestamp, thread, level, content)) = regex_captures!("^([0-9]+-[0-9]{2}-[0-9]{2} [0-9]{2}:[0-9]{2}:[0-9]{2}) \\[([^]]+)/(INFO|WARN|ERROR)\\]: (.+)$", s) {
+            let timestamp = NaiveDateTime::parse_from_str(timestamp, "%Y-%m-%d %H:%M:%S").ok()?;
+            Some((local_timestamp(timestamp), thread, level, content))
+        } else if let Some((_, timestamp, level, content)) = regex_captures!("^([0-9]+-[0-9]{2}-[0-9]{2} [0-9]{2}:[0-9]{2}:[0-9]{2}) \\[(INFO|WARNING|SEVERE)\\] (.+)$", s) {
+            // server.log from before Minecraft 1.7, which doesn't include the thread name
+            let timestamp = NaiveDateTime::parse_from_str(timestamp, "%Y-%m-%d %H:%M:%S").ok()?;
+            Some((local_timestamp(timestamp), "Server thread", level, content))
         } else {
-            Self::Unknown
+            None
+        }
+    }
+}
+
+impl Line {
+    /// Parses a log line in the given format.
+    ///
+    /// `date` is the date of the lines in the log file, used for lines which only have a time-of-day prefix. If it is `None`, the line is assumed to be from the current log file and to have been logged within the last day.
+    async fn parse(state: Arc<RwLock<FollowerState>>, format: LogFormat, date: Option<NaiveDate>, s: &str) -> Result<Self, Error> {
+        let Some((timestamp, thread, level, content)) = format.split(date, s) else { return Ok(Self::Unknown) };
+        let Ok(thread) = thread.parse();
+        let level = level.parse().expect("level that matches regex should parse");
+        Ok(Self::Regular {
+            content: RegularLine::parse(state, &thread, &level, content).await?,
+            timestamp, thread, level,
         })
     }
 }
 
+/// Returns the paths of all logs of the given world which exist, in chronological order.
+///
+/// This includes the `server.log` from before Minecraft 1.7, the archived logs, and `logs/latest.log`.
 async fn history_paths(world: &World) -> Result<Vec<PathBuf>, Error> {
-    let mut logs = fs::read_dir(world.dir().join("logs")).map_ok(|entry| entry.path()).try_collect::<Vec<_>>().await?;
-    logs.sort_unstable_by(|a, b| b.cmp(a));
-    logs.reserve_exact(1);
-    logs.push(world.dir().join("server.log"));
-    Ok(logs)
+    let mut archived = Vec::default();
+    let logs_dir = world.dir().join("logs");
+    if fs::exists(&logs_dir).await? {
+        let mut entries = pin!(fs::read_dir(&logs_dir));
+        while let Some(entry) = entries.try_next().await? {
+            let path = entry.path();
+            // archived logs are named after the date they were started, e.g. 2024-01-31-1.log.gz
+            if let Some((_, date, idx)) = path.file_name().and_then(|file_name| file_name.to_str()).and_then(|file_name| regex_captures!(r"^([0-9]{4}-[0-9]{2}-[0-9]{2})-([0-9]+)\.log(?:\.gz)?$", file_name)) {
+                archived.push(((date.to_owned(), idx.parse::<u32>().expect("number that matches regex should parse")), path));
+            }
+        }
+    }
+    archived.sort_unstable_by(|(key1, _), (key2, _)| key1.cmp(key2));
+    let mut paths = Vec::with_capacity(archived.len() + 2);
+    let legacy_path = world.dir().join("server.log");
+    if fs::exists(&legacy_path).await? { paths.push(legacy_path) }
+    paths.extend(archived.into_iter().map(|(_, path)| path));
+    let latest_path = logs_dir.join("latest.log");
+    if fs::exists(&latest_path).await? { paths.push(latest_path) }
+    Ok(paths)
 }
 
-fn history(http_client: reqwest::Client, world: &World) -> impl Stream<Item = Result<Line, Error>> + '_ {
+/// Reads a log file, decompressing it if necessary. Also returns the date of the lines in the file.
+///
+/// The date is taken from the file name of archived logs. For other files, it's the date the file was last modified, since the server starts a new log at midnight.
+async fn read_log(path: PathBuf) -> Result<(NaiveDate, String), Error> {
+    let date = if let Some(date) = path.file_name().and_then(|file_name| file_name.to_str()).and_then(|file_name| file_name.get(..10)).and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()) {
+        date
+    } else {
+        DateTime::<Local>::from(tokio::fs::metadata(&path).await.at(&path)?.modified().at(&path)?).date_naive()
+    };
+    let contents = if path.extension().is_some_and(|ext| ext == "gz") {
+        let mut buf = String::default();
+        async_compression::tokio::bufread::GzipDecoder::new(BufReader::new(File::open(&path).await?)).read_to_string(&mut buf).await.at(path)?;
+        buf
+    } else {
+        fs::read_to_string(path).await?
+    };
+    Ok((date, contents))
+}
+
+/// Reads the entire log history of the given world in chronological order, from the `server.log` of pre-1.7 versions through the archived logs to `logs/latest.log`. Each line is returned along with its zero-based index in its log file.
+///
+/// The Minecraft version is tracked across server starts so that version-dependent lines like death messages are recognized. This can be used to backfill data from old logs.
+pub fn history(translation_sources: Vec<translations::Source>, format: LogFormat, world: &World) -> impl Stream<Item = Result<(usize, Line), Error>> + '_ {
+    let state = Arc::new(RwLock::new(FollowerState::new(translation_sources)));
     stream::once(history_paths(world))
-        .and_then(move |paths| {
-            let http_client = http_client.clone();
-            future::ok(
-                stream::iter(paths)
-                    .then(|path| async move {
-                        if path.extension().is_some_and(|ext| ext == "gz") {
-                            let mut buf = String::default();
-                            async_compression::tokio::bufread::GzipDecoder::new(BufReader::new(File::open(&path).await?)).read_to_string(&mut buf).await.at(path)?;
-                            Ok(buf)
-                        } else {
-                            fs::read_to_string(path).await
-                        }
-                    })
-                    .and_then(|contents| future::ok(stream::iter(contents.lines().rev().map(|line| line.to_owned()).collect_vec()).map(Ok)))
-                    .try_flatten()
-                    .and_then(move |line| {
-                        let http_client = http_client.clone();
-                        async move {
-                            Line::parse(Arc::new(RwLock::new(FollowerState { // reset state for each line since we're going backwards
-                                minecraft_version: None,
-                                death_messages: HashMap::default(),
-                                http_client,
-                            })), &line).await
-                        }
-                    })
-                    //TODO chain previous logs
-            )
-        })
+        .map_ok(|paths| stream::iter(paths)
+            .then(read_log)
+            .map_ok(|(date, contents)| stream::iter(contents.lines().enumerate().map(|(line_index, line)| Ok::<_, Error>((date, line_index, line.to_owned()))).collect_vec()))
+            .try_flatten()
+        )
         .try_flatten()
+        .and_then(move |(date, line_index, line)| {
+            let state = Arc::clone(&state);
+            async move { Ok((line_index, Line::parse(state, format, Some(date), &line).await?)) }
+        })
 }
 
-/// Follows the log of the given world, starting after the last line break at the time the stream is started.
-fn follow(http_client: reqwest::Client, world: &World) -> impl Stream<Item = Result<Line, Error>> + '_ {
-    let log_path = world.dir().join("logs/latest.log");
-    stream::once(async {
-        let init_lines = LinesStream::new(BufReader::new(File::open(&log_path).await?).lines()).try_fold(0, |acc, _| future::ok(acc + 1)).await?;
-        let chaser = Chaser::new(log_path, chase::Line(init_lines));
-        let stream = ReceiverStream::new(chaser.run())
-            .scan(
-                Arc::new(RwLock::new(FollowerState {
-                    minecraft_version: pin!(history(http_client.clone(), world).try_filter_map(|line| future::ok(if let Line::Regular { content: RegularLine::ServerStart { minecraft_version } } = line {
-                        Some(minecraft_version)
-                    } else {
-                        None
-                    }))).try_next().await?,
-                    death_messages: HashMap::default(),
-                    http_client,
-                })),
-                |state, res| {
-                    let state = Arc::clone(&state);
-                    async move {
-                        Some(match res {
-                            Ok(line) => Line::parse(state, &line).await,
-                            Err(e) => Err(e.into()),
-                        })
-                    }
-                },
-            );
-        Ok::<_, Error>(stream)
-    }).try_flatten()
+/// Returns the Minecraft version with which the given world was most recently started, according to its logs.
+///
+/// Unlike [`history`], this reads the logs backwards and only looks for server start lines.
+async fn latest_version(world: &World) -> Result<Option<String>, Error> {
+    for path in history_paths(world).await?.into_iter().rev() {
+        let (_, contents) = read_log(path).await?;
+        for line in contents.lines().rev() {
+            if let Some((_, version)) = regex_captures!(r"(?:^|\]:? |\) )Starting minecraft server version (.+)$", line) {
+                return Ok(Some(version.to_owned()))
+            }
+        }
+    }
+    Ok(None)
 }
 
-pub async fn handle(ctx_fut: RwFuture<Context>) -> Result<Never, Error> { //TODO dynamically update handled worlds as they are added/removed
-    let http_client = reqwest::Client::builder()
+/// Returns an identifier for the file with the given metadata, used to detect when a log file has been replaced.
+fn file_id(metadata: &std::fs::Metadata) -> Option<u64> {
+    #[cfg(unix)] { Some(std::os::unix::fs::MetadataExt::ino(metadata)) }
+    #[cfg(not(unix))] { let _ = metadata; None }
+}
+
+/// Polls a log file for new lines.
+///
+/// If the file is replaced (e.g. when the server rotates `latest.log` on startup) or truncated, it is reopened and read from the start.
+struct Tail {
+    path: PathBuf,
+    file: tokio::fs::File,
+    file_id: Option<u64>,
+    /// The number of bytes read from the current file so far.
+    pos: u64,
+    /// An incomplete line at the end of the data read so far.
+    partial: Vec<u8>,
+    lines: VecDeque<String>,
+    /// The zero-based index in the current file of the next line to be returned.
+    line_index: usize,
+}
+
+impl Tail {
+    /// Starts following the given file after the last line break at the time this is called.
+    ///
+    /// If the file doesn't exist yet, e.g. because the world has never been started, this waits for it to be created and follows it from the start.
+    async fn new(path: PathBuf) -> Result<Self, Error> {
+        let mut created = false;
+        let mut file = loop {
+            match tokio::fs::File::open(&path).await {
+                Ok(file) => break file,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                    created = true;
+                    sleep(Duration::from_secs(5)).await;
+                }
+                Err(e) => return Err(e).at(&path).map_err(Error::from),
+            }
+        };
+        let file_id = file_id(&file.metadata().await.at(&path)?);
+        let mut buf = Vec::default();
+        if !created {
+            file.read_to_end(&mut buf).await.at(&path)?;
+        }
+        let partial = buf.iter().rposition(|&byte| byte == b'\n').map_or_else(|| buf.clone(), |idx| buf[idx + 1..].to_owned());
+        Ok(Self {
+            pos: buf.len() as u64,
+            lines: VecDeque::default(),
+            line_index: buf.iter().filter(|&&byte| byte == b'\n').count(),
+            path, file, file_id, partial,
+        })
+    }
+
+    /// Returns the next complete line along with its zero-based index in the file.
+    async fn next_line(&mut self) -> Result<(usize, String), Error> {
+        loop {
+            if let Some(line) = self.lines.pop_front() {
+                let line_index = self.line_index;
+                self.line_index += 1;
+                return Ok((line_index, line))
+            }
+            let mut buf = Vec::default();
+            self.file.read_to_end(&mut buf).await.at(&self.path)?;
+            if buf.is_empty() {
+                match tokio::fs::metadata(&self.path).await {
+                    Ok(metadata) => if file_id(&metadata) != self.file_id || metadata.len() < self.pos {
+                        self.file = tokio::fs::File::open(&self.path).await.at(&self.path)?;
+                        self.file_id = file_id(&self.file.metadata().await.at(&self.path)?);
+                        self.pos = 0;
+                        self.partial.clear();
+                        self.line_index = 0;
+                        continue
+                    },
+                    Err(e) if e.kind() == io::ErrorKind::NotFound => {} // the file is being rotated, keep waiting
+                    Err(e) => return Err(e).at(&self.path).map_err(Error::from),
+                }
+                sleep(Duration::from_millis(500)).await;
+            } else {
+                self.pos += buf.len() as u64;
+                self.partial.extend_from_slice(&buf);
+                if let Some(idx) = self.partial.iter().rposition(|&byte| byte == b'\n') {
+                    let rest = self.partial.split_off(idx + 1);
+                    let complete = mem::replace(&mut self.partial, rest);
+                    self.lines.extend(String::from_utf8_lossy(&complete).lines().map(str::to_owned));
+                }
+            }
+        }
+    }
+}
+
+/// Parses the lines of a world's `logs/latest.log` as they're read by the given [`Tail`]. Each line is returned along with its zero-based index in the file.
+fn follow(tail: Tail, state: FollowerState, format: LogFormat) -> impl Stream<Item = Result<(usize, Line), Error>> {
+    stream::try_unfold((tail, Arc::new(RwLock::new(state))), move |(mut tail, state)| async move {
+        let (line_index, line) = tail.next_line().await?;
+        let line = Line::parse(Arc::clone(&state), format, None, &line).await?;
+        Ok(Some(((line_index, line), (tail, state))))
+    })
+}
+
+fn http_client() -> reqwest::Result<reqwest::Client> {
+    reqwest::Client::builder()
         .user_agent(concat!("wurstminebot/", env!("CARGO_PKG_VERSION")))
         .timeout(Duration::from_secs(30))
         .use_rustls_tls()
         .hickory_dns(true)
         .https_only(true)
-        .build()?;
-    let mut handles = Vec::default();
-    for world in World::all().await? {
-        handles.push(tokio::spawn(handle_world(http_client.clone(), ctx_fut.clone(), world)));
+        .build()
+}
+
+/// Stores a chat message, death, or advancement in the database. Other lines are ignored.
+///
+/// `line_index` is the zero-based index of the line in its log file. It's part of the unique key so that identical lines logged within the same second are all stored.
+///
+/// Returns whether a row was inserted or completed, i.e. `false` if the line was already stored or isn't stored at all.
+async fn store_line(pool: &PgPool, world: &World, timestamp: DateTime<Utc>, line_index: usize, content: &RegularLine) -> sqlx::Result<bool> {
+    let line_index = i32::try_from(line_index).expect("log file has too many lines");
+    let result = match content {
+        RegularLine::Chat { sender, msg, is_action } => sqlx::query!("INSERT INTO chat (world, timestamp, line_index, sender, msg, is_action) VALUES ($1, $2, $3, $4, $5, $6) ON CONFLICT DO NOTHING", world.to_string(), timestamp, line_index, sender, msg, is_action).execute(pool).await?,
+        // deaths imported before the key, victim, and killer were recorded are filled in
+        RegularLine::Death { msg, key, victim, killer } => sqlx::query!("INSERT INTO deaths (world, timestamp, line_index, msg, key, victim, killer) VALUES ($1, $2, $3, $4, $5, $6, $7) ON CONFLICT (world, timestamp, line_index, msg) DO UPDATE SET key = EXCLUDED.key, victim = EXCLUDED.victim, killer = EXCLUDED.killer WHERE deaths.key IS NULL", world.to_string(), timestamp, line_index, msg, key, victim, killer.as_deref()).execute(pool).await?,
+        RegularLine::Advancement { kind, player, advancement, .. } => sqlx::query!("INSERT INTO advancements (world, timestamp, line_index, player, advancement, kind) VALUES ($1, $2, $3, $4, $5, $6) ON CONFLICT DO NOTHING", world.to_string(), timestamp, line_index, player, advancement, kind.as_str()).execute(pool).await?,
+        _ => return Ok(false),
+    };
+    Ok(result.rows_affected() > 0)
+}
+
+/// The numbers of lines newly imported by [`backfill`].
+#[derive(Default)]
+pub struct BackfillStats {
+    pub chat: u64,
+    pub deaths: u64,
+    pub advancements: u64,
+    /// Minecraft versions for which no translations could be loaded, so deaths and advancements logged while the server was running them weren't imported.
+    pub missing_translations: Vec<String>,
+}
+
+/// Imports all chat messages, deaths, and advancements from the log history of the given world into the database.
+///
+/// Lines which have already been imported are skipped, so this can safely be run multiple times.
+pub async fn backfill(pool: &PgPool, translations_dir: Option<PathBuf>, format: LogFormat, world: &World) -> Result<BackfillStats, Error> {
+    let mut stats = BackfillStats::default();
+    let history = history(translations::sources(http_client()?, translations_dir, world), format, world);
+    pin_mut!(history);
+    while let Some((line_index, line)) = history.try_next().await? {
+        if let Line::Regular { timestamp, content, .. } = line {
+            if let RegularLine::ServerStart { ref minecraft_version, translations_error: Some(ref e) } = content {
+                eprintln!("failed to load translations for Minecraft {minecraft_version} during backfill of world {world}: {e} ({e:?})");
+                if !stats.missing_translations.contains(minecraft_version) {
+                    stats.missing_translations.push(minecraft_version.clone());
+                }
+            }
+            if store_line(pool, world, timestamp, line_index, &content).await? {
+                match content {
+                    RegularLine::Chat { .. } => stats.chat += 1,
+                    RegularLine::Death { .. } => stats.deaths += 1,
+                    RegularLine::Advancement { .. } => stats.advancements += 1,
+                    _ => {}
+                }
+            }
+        }
+    }
+    Ok(stats)
+}
+
+/// Follows the logs of all worlds.
+///
+/// The list of worlds is checked once per minute, so log handlers are started for newly created worlds and stopped for removed ones.
+pub async fn handle(ctx_fut: RwFuture<Context>) -> Result<Never, Error> {
+    let http_client = http_client()?;
+    let mut tasks = JoinSet::new();
+    let mut abort_handles = HashMap::<String, AbortHandle>::default();
+    loop {
+        let worlds = World::all().await?;
+        abort_handles.retain(|world_name, abort_handle| {
+            let exists = worlds.iter().any(|world| world.to_string() == *world_name);
+            if !exists { abort_handle.abort() }
+            exists
+        });
+        for world in worlds {
+            if let hash_map::Entry::Vacant(entry) = abort_handles.entry(world.to_string()) {
+                entry.insert(tasks.spawn(supervise_world(http_client.clone(), ctx_fut.clone(), world)));
+            }
+        }
+        select! {
+            () = sleep(Duration::from_secs(60)) => {}
+            Some(res) = tasks.join_next() => match res {
+                Ok(never) => match never {},
+                Err(e) if e.is_cancelled() => {} // world was removed
+                Err(e) => return Err(e.into()),
+            },
+        }
+    }
+}
+
+/// Posts a join or leave notice to the world channel, via its webhook if it has exactly one, unless the world is configured to be quiet.
+async fn announce_join_leave(ctx: &Context, config: &crate::config::Config, world: &World, player: String, action: &str) -> Result<(), Error> {
+    if config.wurstminebot.quiet_worlds.contains(&world.to_string()) { return Ok(()) }
+    if let Some(chan_id) = config.wurstminebot.world_channels.get(&world.to_string()) {
+        if let Ok(webhook) = chan_id.webhooks(ctx).await?.into_iter().exactly_one() {
+            webhook.execute(ctx, false, ExecuteWebhook::new()
+                .avatar_url(format!("https://minotar.net/armor/bust/{player}/1024.png"))
+                .content(MessageBuilder::default().push_italic(action).build())
+                .username(player)
+            ).await?;
+        } else {
+            chan_id.say(ctx, MessageBuilder::default().push_safe(player).push(' ').push(action).build()).await?;
+        }
+    }
+    Ok(())
+}
+
+/// The information shown in a world channel's topic in addition to the configured topic.
+struct TopicInfo {
+    minecraft_version: Option<String>,
+    online: usize,
+}
+
+/// Keeps the topic of the given world's channel up to date.
+///
+/// Discord only allows 2 topic edits per channel per 10 minutes, so changes are coalesced and applied at most once every 5 minutes.
+async fn update_topic(ctx_fut: &RwFuture<Context>, world: &World, mut info_rx: watch::Receiver<TopicInfo>) -> Result<Never, Error> {
+    loop {
+        info_rx.changed().await.map_err(|_| Error::FollowEnded)?;
+        let topic = {
+            let info = info_rx.borrow_and_update();
+            let Some(ref minecraft_version) = info.minecraft_version else { continue };
+            let online = match info.online {
+                0 => format!("nobody online"),
+                1 => format!("1 player online"),
+                online => format!("{online} players online"),
+            };
+            format!("currently running on {minecraft_version}, {online}")
+        };
+        {
+            let ctx = ctx_fut.read().await;
+            let ctx_data = (*ctx).data.read().await;
+            let config = ctx_data.get::<crate::config::Config>().expect("missing config");
+            if let Some(chan_id) = config.wurstminebot.world_channels.get(&world.to_string()) {
+                if let Some(prefix) = config.wurstminebot.world_channel_topics.get(&world.to_string()) {
+                    chan_id.edit(&*ctx, EditChannel::new().topic(format!("{prefix}, {topic}"))).await?;
+                }
+            }
+        }
+        sleep(Duration::from_secs(5 * 60)).await;
+    }
+}
+
+async fn say_in_world_channel(ctx: &Context, world: &World, content: impl Into<String>) -> Result<(), Error> {
+    let ctx_data = ctx.data.read().await;
+    if let Some(chan_id) = ctx_data.get::<crate::config::Config>().expect("missing config").wurstminebot.world_channels.get(&world.to_string()) {
+        chan_id.say(ctx, content).await?;
+    }
+    Ok(())
+}
+
+/// Extracts the description and the first lines of the stack trace from a Minecraft crash report.
+fn crash_summary(report: &str) -> (Option<&str>, String) {
+    let mut lines = report.lines().skip_while(|line| !line.starts_with("Description: "));
+    let description = lines.next().and_then(|line| line.strip_prefix("Description: "));
+    let stack_trace = lines
+        .skip_while(|line| line.trim().is_empty())
+        .take_while(|line| !line.trim().is_empty())
+        .take(12)
+        .join("\n");
+    (description, stack_trace)
+}
+
+/// Checks the world's `crash-reports` directory once per minute and reports any new crash reports.
+async fn watch_crash_reports(ctx_fut: &RwFuture<Context>, world: &World) -> Result<Never, Error> {
+    async fn crash_report_paths(dir: &Path) -> Result<HashSet<PathBuf>, Error> {
+        Ok(if fs::exists(dir).await? {
+            fs::read_dir(dir).map_ok(|entry| entry.path()).try_collect::<HashSet<_>>().await?
+        } else {
+            HashSet::default()
+        })
+    }
+
+    let dir = world.dir().join("crash-reports");
+    let mut known = crash_report_paths(&dir).await?;
+    loop {
+        sleep(Duration::from_secs(60)).await;
+        for path in crash_report_paths(&dir).await? {
+            if known.contains(&path) { continue }
+            let report = fs::read_to_string(&path).await?;
+            let (description, stack_trace) = crash_summary(&report);
+            let ctx = ctx_fut.read().await;
+            say_in_world_channel(&ctx, world, "the server crashed").await?;
+            let mut msg = MessageBuilder::default();
+            msg.push("new crash report ");
+            msg.push_mono_safe(path.file_name().map_or_else(|| path.display().to_string(), |file_name| file_name.to_string_lossy().into_owned()));
+            msg.push(" for world ");
+            msg.push_safe(world.to_string());
+            if let Some(description) = description {
+                msg.push(": ");
+                msg.push_safe(description);
+            }
+            if !stack_trace.is_empty() {
+                msg.push_codeblock_safe(stack_trace.chars().take(1500).collect::<String>(), None);
+            }
+            DEV.say(&*ctx, msg.build()).await?;
+            known.insert(path);
+        }
+    }
+}
+
+/// Runs the log handler for the given world, restarting it with exponential backoff if it fails so that other worlds aren't affected.
+async fn supervise_world(http_client: reqwest::Client, ctx_fut: RwFuture<Context>, world: World) -> Never {
+    let mut last_crash = None::<Instant>;
+    let mut wait_time = Duration::from_secs(1);
+    loop {
+        let Err(e) = handle_world(http_client.clone(), &ctx_fut, &world).await;
+        if last_crash.is_some_and(|last_crash| last_crash.elapsed() < Duration::from_secs(60 * 60 * 24)) {
+            wait_time *= 2; // exponential backoff
+        } else {
+            wait_time = Duration::from_secs(1); // first crash, or no crash for a day
+        }
+        eprintln!("log handler for world {world} crashed: {e} ({e:?})");
+        if wait_time >= Duration::from_secs(2) { // only notify on multiple consecutive errors
+            let ctx = ctx_fut.read().await;
+            if let Err(e) = DEV.say(&*ctx, MessageBuilder::default()
+                .push("log handler for world ")
+                .push_safe(world.to_string())
+                .push(" crashed: ")
+                .push_mono_safe(e.to_string())
+                .push(format!(", restarting in {wait_time:?}"))
+                .build()
+            ).await {
+                eprintln!("failed to notify about log handler crash: {e} ({e:?})");
+            }
+        }
+        sleep(wait_time).await; // wait before attempting to restart
+        last_crash = Some(Instant::now());
     }
-    match try_join_all(handles).await?.pop() {
-        Some(Ok(never)) => match never {},
-        Some(Err(e)) => Err(e),
-        None => Err(Error::NoWorlds),
+}
+
+async fn handle_world(http_client: reqwest::Client, ctx_fut: &RwFuture<Context>, world: &World) -> Result<Never, Error> {
+    let (running, online) = {
+        let ctx = ctx_fut.read().await;
+        let ctx_data = (*ctx).data.read().await;
+        let pool = ctx_data.get::<Database>().expect("missing database connection");
+        let (running, online) = match minecraft::list(world).await {
+            Ok(online) => (true, online),
+            Err(crate::Error::Minecraft(_)) => (false, Vec::default()), // world not running
+            Err(e) => return Err(Box::new(e).into()),
+        };
+        sessions::reconcile(pool, world, &online).await?;
+        (running, online.len())
+    };
+    let (info_tx, mut info_rx) = watch::channel(TopicInfo {
+        minecraft_version: latest_version(world).await?,
+        online,
+    });
+    info_rx.mark_changed();
+    select! {
+        res = update_topic(ctx_fut, world, info_rx) => res,
+        res = watch_crash_reports(ctx_fut, world) => res,
+        res = follow_world(http_client, ctx_fut, world, running, info_tx) => res,
     }
 }
 
-async fn handle_world(http_client: reqwest::Client, ctx_fut: RwFuture<Context>, world: World) -> Result<Never, Error> {
-    let follower = follow(http_client, &world);
+/// Reports that the given world stopped without logging a stop line, e.g. because it crashed or was killed, and ends the sessions of the players who were online.
+async fn report_unclean_stop(ctx_fut: &RwFuture<Context>, world: &World, description: &str) -> Result<(), Error> {
+    let ctx = ctx_fut.read().await;
+    say_in_world_channel(&ctx, world, "the server stopped unexpectedly").await?;
+    DEV.say(&*ctx, MessageBuilder::default().push("world ").push_safe(world.to_string()).push(' ').push(description).build()).await?;
+    let ctx_data = (*ctx).data.read().await;
+    sessions::reconcile(ctx_data.get::<Database>().expect("missing database connection"), world, &[]).await?;
+    Ok(())
+}
+
+/// Reports that the translations for the given Minecraft version couldn't be loaded, so deaths and advancements aren't recognized while the world runs it.
+async fn report_translations_error(ctx_fut: &RwFuture<Context>, world: &World, minecraft_version: &str, e: &Error) -> Result<(), Error> {
+    eprintln!("failed to load translations for Minecraft {minecraft_version} in world {world}: {e} ({e:?})");
+    let ctx = ctx_fut.read().await;
+    DEV.say(&*ctx, MessageBuilder::default()
+        .push("failed to load translations for Minecraft ")
+        .push_safe(minecraft_version)
+        .push(" in world ")
+        .push_safe(world.to_string())
+        .push(", deaths and advancements won't be recognized: ")
+        .push_mono_safe(e.to_string())
+        .build()
+    ).await?;
+    Ok(())
+}
+
+/// `running` is whether the server was running when the bot started. It's used to detect when the server stops without logging a stop line, e.g. because it was killed.
+async fn follow_world(http_client: reqwest::Client, ctx_fut: &RwFuture<Context>, world: &World, mut running: bool, info_tx: watch::Sender<TopicInfo>) -> Result<Never, Error> {
+    let (translations_dir, format) = {
+        let ctx = ctx_fut.read().await;
+        let ctx_data = (*ctx).data.read().await;
+        let config = ctx_data.get::<crate::config::Config>().expect("missing config");
+        (config.wurstminebot.translations_dir.clone(), config.wurstminebot.log_formats.get(&world.to_string()).copied().unwrap_or_default())
+    };
+    let lag = {
+        let ctx = ctx_fut.read().await;
+        let mut ctx_data = (*ctx).data.write().await;
+        // the entry is kept across restarts of the log handler so the window isn't reset
+        Arc::clone(ctx_data.get_mut::<LagStats>().expect("missing lag stats").entry(world.to_string()).or_default())
+    };
+    let tail = Tail::new(world.dir().join("logs/latest.log")).await?;
+    let mut state = FollowerState::new(translations::sources(http_client, translations_dir, world));
+    // the server start line may be long gone, so the translations for the running version are loaded up front
+    if let Some(version) = latest_version(world).await? {
+        if let Err(e) = state.set_version(&version).await {
+            report_translations_error(ctx_fut, world, &version, &e).await?;
+        }
+    }
+    let follower = follow(tail, state, format);
     pin_mut!(follower);
-    while let Some(line) = follower.try_next().await? {
+    // warnings and errors which have been forwarded to #dev in the last hour, to avoid flooding the channel with repeated messages
+    let mut forwarded = HashMap::<String, Instant>::default();
+    // a crashed or killed server doesn't log anything, so the service status is checked periodically
+    let mut status_check = interval(Duration::from_secs(60));
+    status_check.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    loop {
+        let line = select! {
+            // lines are handled first so a clean stop is seen before the service becomes inactive
+            biased;
+            line = follower.try_next() => line?,
+            _ = status_check.tick(), if running => {
+                if !minecraft::is_running(world).await? {
+                    running = false;
+                    report_unclean_stop(ctx_fut, world, "stopped without logging a clean stop").await?;
+                    info_tx.send_modify(|info| info.online = 0);
+                }
+                continue
+            }
+        };
+        let Some((line_index, line)) = line else { break };
+        if let Line::Regular { timestamp, ref content, .. } = line {
+            let ctx = ctx_fut.read().await;
+            let ctx_data = (*ctx).data.read().await;
+            // the line is still relayed to Discord, and it can be imported later using the backfill command
+            if let Err(e) = store_line(ctx_data.get::<Database>().expect("missing database connection"), world, timestamp, line_index, content).await {
+                eprintln!("failed to store log line from world {world}: {e} ({e:?})");
+            }
+        }
         match line {
-            Line::Regular { content, .. } => match content {
-                RegularLine::ServerStart { minecraft_version } => {
+            Line::Regular { timestamp, thread, level, content } => match content {
+                RegularLine::ServerStart { minecraft_version, translations_error } => {
+                    if let Some(e) = translations_error {
+                        report_translations_error(ctx_fut, world, &minecraft_version, &e).await?;
+                    }
+                    if running {
+                        // restarted before the status check noticed
+                        report_unclean_stop(ctx_fut, world, "was restarted without logging a clean stop").await?;
+                    } else {
+                        let ctx = ctx_fut.read().await;
+                        let ctx_data = (*ctx).data.read().await;
+                        sessions::reconcile(ctx_data.get::<Database>().expect("missing database connection"), world, &[]).await?;
+                    }
+                    running = true;
+                    info_tx.send_replace(TopicInfo {
+                        minecraft_version: Some(minecraft_version),
+                        online: 0,
+                    });
+                }
+                RegularLine::Done { startup_time } => say_in_world_channel(&*ctx_fut.read().await, world, format!("the server is up (started in {startup_time})")).await?,
+                RegularLine::Stopping => {
+                    running = false;
+                    say_in_world_channel(&*ctx_fut.read().await, world, "the server is stopping").await?;
+                }
+                RegularLine::Lag { ms_behind, ticks_behind } => {
                     let ctx = ctx_fut.read().await;
-                    let ctx_data = (*ctx).data.read().await;
-                    let config = ctx_data.get::<crate::config::Config>().expect("missing config");
-                    if let Some(chan_id) = config.wurstminebot.world_channels.get(&world.to_string()) {
-                        if let Some(topic) = config.wurstminebot.world_channel_topics.get(&world.to_string()) {
-                            chan_id.edit(&*ctx, EditChannel::new().topic(format!("{topic}, currently running on {minecraft_version}"))).await?;
+                    let (threshold_ms, alert_world_channel) = {
+                        let ctx_data = (*ctx).data.read().await;
+                        let config = ctx_data.get::<crate::config::Config>().expect("missing config");
+                        (config.wurstminebot.lag_alert_threshold_ms, config.wurstminebot.lag_alert_worlds.contains(&world.to_string()))
+                    };
+                    let alert = {
+                        let mut lag = lag.write().await;
+                        lag.record(timestamp, ms_behind, ticks_behind);
+                        lag.check_alert(timestamp, threshold_ms)
+                    };
+                    if let Some(summary) = alert {
+                        let description = format!(
+                            "{} \"Can't keep up!\" warnings in the last 10 minutes, {:.1} seconds ({} ticks) behind in total, up to {:.1} seconds at once",
+                            summary.warnings,
+                            summary.ms_behind as f64 / 1000.0,
+                            summary.ticks_behind,
+                            summary.max_ms_behind as f64 / 1000.0,
+                        );
+                        DEV.say(&*ctx, MessageBuilder::default().push("world ").push_safe(world.to_string()).push(" is lagging: ").push(&*description).build()).await?;
+                        if alert_world_channel {
+                            say_in_world_channel(&ctx, world, format!("the server is lagging: {description}")).await?;
                         }
                     }
                 }
@@ -417,33 +1150,269 @@ async fn handle_world(http_client: reqwest::Client, ctx_fut: RwFuture<Context>,
                         }
                     }
                 }
-                RegularLine::Advancement { kind, player, advancement } => {
+                RegularLine::Advancement { kind, player, advancement, candidates } => {
                     let ctx = ctx_fut.read().await;
                     let ctx_data = (*ctx).data.read().await;
                     if let Some(chan_id) = ctx_data.get::<crate::config::Config>().expect("missing config").wurstminebot.world_channels.get(&world.to_string()) {
-                        chan_id.say(&*ctx, MessageBuilder::default()
-                            .push_safe(player)
-                            .push(match kind {
-                                AdvancementKind::Challenge => " has completed the challenge [",
-                                AdvancementKind::Goal => " has reached the goal [",
-                                AdvancementKind::Task => " has made the advancement [",
+                        let mut embed = CreateEmbed::new()
+                            .colour(match kind {
+                                // same colours as the advancement names in chat, except that goals are gold since they would have the same colour as tasks
+                                AdvancementKind::Challenge => Colour(0xaa00aa),
+                                AdvancementKind::Goal => Colour(0xffaa00),
+                                AdvancementKind::Task => Colour(0x55ff55),
                             })
-                            .push_safe(advancement)
-                            .push(']')
-                            .build()).await?;
+                            .title(&advancement)
+                            .url(Url::parse_with_params("https://minecraft.wiki/w/Special:Search", [("search", &*advancement)]).expect("wiki search URL should parse"))
+                            .thumbnail(format!("https://minotar.net/helm/{player}/64.png"))
+                            .field("frame", kind.as_str(), true);
+                        match &*candidates {
+                            [] => {}
+                            [AdvancementInfo { key, description }] => embed = embed
+                                .description(description)
+                                .footer(CreateEmbedFooter::new(key)),
+                            _ => for AdvancementInfo { key, description } in &candidates {
+                                embed = embed.field(key, description, false);
+                            },
+                        }
+                        chan_id.send_message(&*ctx, CreateMessage::new()
+                            .content(MessageBuilder::default()
+                                .push_safe(&player)
+                                .push(match kind {
+                                    AdvancementKind::Challenge => " has completed the challenge [",
+                                    AdvancementKind::Goal => " has reached the goal [",
+                                    AdvancementKind::Task => " has made the advancement [",
+                                })
+                                .push_safe(&advancement)
+                                .push(']')
+                                .build())
+                            .embed(embed)
+                        ).await?;
                     }
                 }
-                RegularLine::Death { msg, .. } => {
+                RegularLine::Death { msg, key, victim, .. } => {
                     let ctx = ctx_fut.read().await;
                     let ctx_data = (*ctx).data.read().await;
                     if let Some(chan_id) = ctx_data.get::<crate::config::Config>().expect("missing config").wurstminebot.world_channels.get(&world.to_string()) {
-                        chan_id.say(&*ctx, msg).await?;
+                        chan_id.send_message(&*ctx, CreateMessage::new()
+                            .embed(CreateEmbed::new()
+                                .colour(Colour(0xaa0000))
+                                .title(msg)
+                                .url("https://minecraft.wiki/w/Death_messages")
+                                .thumbnail(format!("https://minotar.net/helm/{victim}/64.png"))
+                                .footer(CreateEmbedFooter::new(key))
+                            )
+                        ).await?;
+                    }
+                }
+                RegularLine::Join { player } => {
+                    let ctx = ctx_fut.read().await;
+                    let ctx_data = (*ctx).data.read().await;
+                    sessions::start(ctx_data.get::<Database>().expect("missing database connection"), world, &player, timestamp).await?;
+                    info_tx.send_modify(|info| info.online += 1);
+                    announce_join_leave(&ctx, ctx_data.get::<crate::config::Config>().expect("missing config"), world, player, "joined the game").await?;
+                }
+                RegularLine::Leave { player } => {
+                    let ctx = ctx_fut.read().await;
+                    let ctx_data = (*ctx).data.read().await;
+                    sessions::end(ctx_data.get::<Database>().expect("missing database connection"), world, &player, timestamp).await?;
+                    info_tx.send_modify(|info| info.online = info.online.saturating_sub(1));
+                    announce_join_leave(&ctx, ctx_data.get::<crate::config::Config>().expect("missing config"), world, player, "left the game").await?;
+                }
+                RegularLine::Say { sender, msg } => say_in_world_channel(&*ctx_fut.read().await, world, MessageBuilder::default()
+                    .push('[')
+                    .push_safe(sender)
+                    .push("] ")
+                    .push_safe(msg)
+                    .build()
+                ).await?,
+                RegularLine::CommandFeedback { sender, msg } => {
+                    let ctx = ctx_fut.read().await;
+                    let ctx_data = (*ctx).data.read().await;
+                    if let Some(chan_id) = ctx_data.get::<crate::config::Config>().expect("missing config").wurstminebot.op_feedback_channel {
+                        chan_id.say(&*ctx, MessageBuilder::default()
+                            .push_safe(world.to_string())
+                            .push(": [")
+                            .push_safe(sender)
+                            .push(": ")
+                            .push_safe(msg)
+                            .push(']')
+                            .build()
+                        ).await?;
                     }
                 }
-                RegularLine::Unknown => {} // ignore all other lines for now
+                RegularLine::Unknown { msg } => if let (Thread::Server, Level::Warn | Level::Error) = (&thread, &level) {
+                    let now = Instant::now();
+                    forwarded.retain(|_, last_forwarded| now.duration_since(*last_forwarded) < Duration::from_secs(60 * 60));
+                    if !forwarded.contains_key(&msg) {
+                        let ctx = ctx_fut.read().await;
+                        DEV.say(&*ctx, MessageBuilder::default()
+                            .push(format!("<t:{}:T> ", timestamp.timestamp()))
+                            .push_safe(world.to_string())
+                            .push(if let Level::Error = level { " error: " } else { " warning: " })
+                            .push_mono_safe(&*msg)
+                            .build()
+                        ).await?;
+                        forwarded.insert(msg, now);
+                    }
+                },
             },
             Line::Unknown => {} // ignore all other lines for now
         }
     }
     Err(Error::FollowEnded)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 1, 31).expect("valid date")
+    }
+
+    fn timestamp(h: u32, m: u32, s: u32, milli: u32) -> DateTime<Utc> {
+        local_timestamp(date().and_hms_milli_opt(h, m, s, milli).expect("valid time"))
+    }
+
+    fn state() -> Arc<RwLock<FollowerState>> {
+        let mut state = FollowerState::new(Vec::default());
+        for (key, format) in [
+            ("death.attack.badRespawnPoint.message", "%1$s was killed by %2$s"),
+            ("death.attack.drown", "%1$s drowned"),
+            ("death.attack.magic", "%1$s was killed by magic"),
+            ("death.attack.mob", "%1$s was slain by %2$s"),
+            ("death.attack.mob.item", "%1$s was slain by %2$s using %3$s"),
+            ("death.attack.player", "%1$s was slain by %2$s"),
+        ] {
+            state.death_messages.insert(key.to_owned(), DeathMessage::new(format).expect("valid format"));
+        }
+        state.advancements.insert(format!("Stone Age"), vec![AdvancementInfo {
+            key: format!("advancements.story.mine_stone"),
+            description: format!("Mine Stone with your new Pickaxe"),
+        }]);
+        Arc::new(RwLock::new(state))
+    }
+
+    #[test]
+    fn split_vanilla() {
+        assert_eq!(
+            LogFormat::Vanilla.split(Some(date()), "[12:34:56] [Server thread/INFO]: <Alice> hello"),
+            Some((timestamp(12, 34, 56, 0), "Server thread", "INFO", "<Alice> hello")),
+        );
+        assert_eq!(
+            LogFormat::Vanilla.split(Some(date()), "[12:34:56] [User Authenticator #1/WARN]: Can't verify"),
+            Some((timestamp(12, 34, 56, 0), "User Authenticator #1", "WARN", "Can't verify")),
+        );
+        assert_eq!(LogFormat::Vanilla.split(Some(date()), "\tat java.lang.Thread.run(Thread.java:833)"), None);
+    }
+
+    #[test]
+    fn split_paper() {
+        assert_eq!(
+            LogFormat::Paper.split(Some(date()), "[12:34:56 INFO]: Alice joined the game"),
+            Some((timestamp(12, 34, 56, 0), "Server thread", "INFO", "Alice joined the game")),
+        );
+        // from before the world switched to Paper
+        assert_eq!(
+            LogFormat::Paper.split(Some(date()), "[12:34:56] [Server thread/INFO]: Alice joined the game"),
+            Some((timestamp(12, 34, 56, 0), "Server thread", "INFO", "Alice joined the game")),
+        );
+    }
+
+    #[test]
+    fn split_fabric() {
+        assert_eq!(
+            LogFormat::Fabric.split(Some(date()), "[12:34:56] [Server thread/INFO] (Minecraft) <Alice> hello"),
+            Some((timestamp(12, 34, 56, 0), "Server thread", "INFO", "<Alice> hello")),
+        );
+    }
+
+    #[test]
+    fn split_forge() {
+        assert_eq!(
+            LogFormat::Forge.split(Some(date()), "[12:34:56] [Server thread/INFO] [minecraft/DedicatedServer]: Stopping server"),
+            Some((timestamp(12, 34, 56, 0), "Server thread", "INFO", "Stopping server")),
+        );
+        assert_eq!(
+            LogFormat::Forge.split(None, "[31Jan2024 12:34:56.789] [Server thread/INFO] [net.minecraft.server.dedicated.DedicatedServer/]: Stopping server"),
+            Some((timestamp(12, 34, 56, 789), "Server thread", "INFO", "Stopping server")),
+        );
+    }
+
+    #[test]
+    fn split_old_formats() {
+        assert_eq!(
+            LogFormat::Vanilla.split(None, "2024-01-31 12:34:56 [Server thread/INFO]: <Alice> hello"),
+            Some((timestamp(12, 34, 56, 0), "Server thread", "INFO", "<Alice> hello")),
+        );
+        // server.log from before Minecraft 1.7
+        assert_eq!(
+            LogFormat::Vanilla.split(None, "2024-01-31 12:34:56 [WARNING] Can't keep up! Did the system time change, or is the server overloaded?"),
+            Some((timestamp(12, 34, 56, 0), "Server thread", "WARNING", "Can't keep up! Did the system time change, or is the server overloaded?")),
+        );
+    }
+
+    #[tokio::test]
+    async fn parse_chat() -> Result<(), Error> {
+        let RegularLine::Chat { sender, msg, is_action } = RegularLine::parse(state(), &Thread::Server, &Level::Info, "<Alice> hello").await? else { panic!("not parsed as chat") };
+        assert_eq!((&*sender, &*msg, is_action), ("Alice", "hello", false));
+        let RegularLine::Chat { sender, msg, is_action } = RegularLine::parse(state(), &Thread::Server, &Level::Info, "[Not Secure] * Alice waves").await? else { panic!("not parsed as chat") };
+        assert_eq!((&*sender, &*msg, is_action), ("Alice", "waves", true));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn parse_join_leave() -> Result<(), Error> {
+        let RegularLine::Join { player } = RegularLine::parse(state(), &Thread::Server, &Level::Info, "Alice joined the game").await? else { panic!("not parsed as join") };
+        assert_eq!(player, "Alice");
+        let RegularLine::Leave { player } = RegularLine::parse(state(), &Thread::Server, &Level::Info, "Alice left the game").await? else { panic!("not parsed as leave") };
+        assert_eq!(player, "Alice");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn parse_death() -> Result<(), Error> {
+        let RegularLine::Death { msg, key, victim, killer } = RegularLine::parse(state(), &Thread::Server, &Level::Info, "Alice drowned").await? else { panic!("not parsed as death") };
+        assert_eq!((&*msg, &*key, &*victim, killer), ("Alice drowned", "death.attack.drown", "Alice", None));
+        // the most specific format is preferred
+        let RegularLine::Death { key, victim, killer, .. } = RegularLine::parse(state(), &Thread::Server, &Level::Info, "Alice was slain by Zombie using [Sword]").await? else { panic!("not parsed as death") };
+        assert_eq!((&*key, &*victim, killer.as_deref()), ("death.attack.mob.item", "Alice", Some("Zombie")));
+        let RegularLine::Death { key, killer, .. } = RegularLine::parse(state(), &Thread::Server, &Level::Info, "Alice was killed by [Intentional Game Design]").await? else { panic!("not parsed as death") };
+        assert_eq!((&*key, killer), ("death.attack.badRespawnPoint.message", None));
+        // literal text is preferred over placeholders
+        let RegularLine::Death { key, killer, .. } = RegularLine::parse(state(), &Thread::Server, &Level::Info, "Alice was killed by magic").await? else { panic!("not parsed as death") };
+        assert_eq!((&*key, killer), ("death.attack.magic", None));
+        // identical formats are ranked by key, regardless of iteration order
+        for _ in 0..10 {
+            let RegularLine::Death { key, .. } = RegularLine::parse(state(), &Thread::Server, &Level::Info, "Alice was slain by Bob").await? else { panic!("not parsed as death") };
+            assert_eq!(key, "death.attack.mob");
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn parse_advancement() -> Result<(), Error> {
+        let RegularLine::Advancement { kind, player, advancement, candidates } = RegularLine::parse(state(), &Thread::Server, &Level::Info, "Alice has made the advancement [Stone Age]").await? else { panic!("not parsed as advancement") };
+        assert!(matches!(kind, AdvancementKind::Task));
+        assert_eq!((&*player, &*advancement), ("Alice", "Stone Age"));
+        assert_eq!(candidates.iter().map(|info| &*info.key).collect_vec(), ["advancements.story.mine_stone"]);
+        let RegularLine::Advancement { kind, candidates, .. } = RegularLine::parse(state(), &Thread::Server, &Level::Info, "Alice has completed the challenge [Data Pack Challenge]").await? else { panic!("not parsed as advancement") };
+        assert!(matches!(kind, AdvancementKind::Challenge));
+        assert!(candidates.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn parse_say() -> Result<(), Error> {
+        let RegularLine::Say { sender, msg } = RegularLine::parse(state(), &Thread::Server, &Level::Info, "[Server] restarting in 5 minutes").await? else { panic!("not parsed as /say") };
+        assert_eq!((&*sender, &*msg), ("Server", "restarting in 5 minutes"));
+        let RegularLine::Say { sender, .. } = RegularLine::parse(state(), &Thread::Server, &Level::Info, "[Alice] hi everyone").await? else { panic!("not parsed as /say") };
+        assert_eq!(sender, "Alice");
+        let RegularLine::CommandFeedback { sender, msg } = RegularLine::parse(state(), &Thread::Server, &Level::Info, "[Alice: Set the time to 1000]").await? else { panic!("not parsed as command feedback") };
+        assert_eq!((&*sender, &*msg), ("Alice", "Set the time to 1000"));
+        // e.g. output from a mod
+        assert!(matches!(RegularLine::parse(state(), &Thread::Unknown, &Level::Info, "[Alice] hi everyone").await?, RegularLine::Unknown { .. }));
+        assert!(matches!(RegularLine::parse(state(), &Thread::Server, &Level::Warn, "[Alice: Set the time to 1000]").await?, RegularLine::Unknown { .. }));
+        Ok(())
+    }
+}
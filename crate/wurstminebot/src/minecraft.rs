@@ -1,9 +1,54 @@
 use {
+    chrono_tz::Tz,
+    itertools::Itertools as _,
     minecraft::chat::Chat,
+    sqlx::PgPool,
     systemd_minecraft::World,
-    crate::Error,
+    tokio::{
+        io,
+        process::Command,
+    },
+    crate::{
+        Error,
+        people,
+    },
 };
 
+/// Returns whether the systemd service for the given world is active.
+pub async fn is_running(world: &World) -> io::Result<bool> { //TODO move to systemd-minecraft
+    Ok(Command::new("systemctl").arg("is-active").arg("--quiet").arg(format!("minecraft@{world}.service")).status().await?.success())
+}
+
 pub async fn tellraw(world: &World, rcpt: &str, msg: &Chat) -> Result<String, Error> { //TODO move to systemd-minecraft
     Ok(world.command(&format!("tellraw {} {}", rcpt, msg)).await?)
 }
+
+/// Returns the Minecraft nicknames of the players who are currently online in the given world.
+pub async fn list(world: &World) -> Result<Vec<String>, Error> { //TODO move to systemd-minecraft
+    let output = world.command("list").await?;
+    // e.g. "There are 2 of a max of 20 players online: Alice, Bob"
+    let Some((_, players)) = output.split_once(':') else { return Err(Error::MalformedPlayerList(output)) };
+    Ok(players.split(',').map(str::trim).filter(|player| !player.is_empty()).map(str::to_owned).collect())
+}
+
+/// Sends a message to each player who is online in the given world, rendered in that player's timezone.
+///
+/// The message is built separately for each timezone. Players who haven't set a timezone using `/timezone`, as well as players who join while the message is being sent, receive the message in UTC.
+pub async fn tellraw_localized(pool: &PgPool, world: &World, mut msg: impl FnMut(Tz) -> Chat) -> Result<(), Error> {
+    let players = list(world).await?;
+    for player in &players {
+        let timezone = people::minecraft_timezone(pool, player).await?.unwrap_or(Tz::UTC);
+        tellraw(world, player, &msg(timezone)).await?;
+    }
+    tellraw(world, &unlisted_players(&players), &msg(Tz::UTC)).await?;
+    Ok(())
+}
+
+/// Returns a target selector for the players who are online but not in the given list, e.g. because they joined after [`list`] was called.
+pub fn unlisted_players(players: &[String]) -> String {
+    if players.is_empty() {
+        format!("@a")
+    } else {
+        format!("@a[{}]", players.iter().map(|player| format!("name=!{player}")).join(","))
+    }
+}
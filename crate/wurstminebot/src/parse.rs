@@ -1,5 +1,6 @@
 use {
     std::str::FromStr as _,
+    chrono::prelude::*,
     itertools::Itertools as _,
     lazy_regex::regex_captures,
     serenity::model::prelude::*,
@@ -7,6 +8,20 @@ use {
     crate::people::PersonId,
 };
 
+/// Parses a date and time given as a command argument.
+///
+/// Accepts Discord timestamp markup (e.g. `<t:1700000000:F>`), RFC 3339, and `YYYY-MM-DD HH:MM`, which is interpreted as UTC.
+pub fn datetime(s: &str) -> Option<DateTime<Utc>> {
+    let s = s.trim();
+    if let Some((_, timestamp)) = regex_captures!("^<t:(-?[0-9]+)(?::[tTdDfFR])?>$", s) {
+        DateTime::from_timestamp(timestamp.parse().ok()?, 0)
+    } else if let Ok(datetime) = DateTime::parse_from_rfc3339(s) {
+        Some(datetime.with_timezone(&Utc))
+    } else {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").ok().map(|datetime| datetime.and_utc())
+    }
+}
+
 pub fn eat_optional_prefix(cmd: &mut &str, prefix: char) -> bool {
     if cmd.starts_with(prefix) {
         *cmd = &cmd[1..];
@@ -1,11 +1,16 @@
 //! Model types.
 
 use {
+    chrono_tz::Tz,
     futures::stream::{
         StreamExt as _,
         TryStreamExt as _,
     },
-    serde::Deserialize,
+    serde::{
+        Deserialize,
+        Serialize,
+        Serializer,
+    },
     serde_json::json,
     serenity::model::prelude::*,
     sqlx::{
@@ -21,6 +26,16 @@ pub enum PersonId {
     Discord(UserId),
 }
 
+impl Serialize for PersonId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::LegacyWurstmineberg(wmbid) => wmbid.serialize(serializer),
+            // serenity serializes IDs as strings, which would be read back as legacy Wurstmineberg IDs
+            Self::Discord(user_id) => user_id.get().serialize(serializer),
+        }
+    }
+}
+
 impl PersonId {
     /// Attempts to look up a Person from a name and optional discriminator.
     ///
@@ -59,7 +74,7 @@ impl PersonId {
         })
     }
 
-    pub(crate) async fn display(&self, pool: &PgPool) -> String {
+    pub async fn display(&self, pool: &PgPool) -> String {
         match self {
             Self::Discord(user_id) => match sqlx::query!(r#"SELECT discorddata->'username' as "username!: Json<String>", discorddata->'nick' as "nick: Json<Option<String>>" FROM people WHERE snowflake = $1"#, i64::from(*user_id)).fetch_one(pool).await {
                 Ok(row) => row.nick.and_then(|nick| nick.0).unwrap_or_else(|| row.username.0),
@@ -73,3 +88,29 @@ impl PersonId {
         }
     }
 }
+
+/// Returns the timezone the Person currently using the given Minecraft nickname has set using `/timezone`, if any.
+pub async fn minecraft_timezone(pool: &PgPool, minecraft_nick: &str) -> sqlx::Result<Option<Tz>> {
+    Ok(sqlx::query_scalar!(r#"SELECT data->'timezone' as "timezone: Json<String>" FROM people WHERE data->'minecraft'->'nicks'->>-1 = $1"#, minecraft_nick).fetch_optional(pool).await?
+        .flatten()
+        .and_then(|Json(timezone)| timezone.parse().ok()))
+}
+
+/// Sets or (if `None` is given) resets the timezone of the Person with the given Discord user ID.
+///
+/// Returns `false` if the given Discord user is not a Person.
+pub async fn set_timezone(pool: &PgPool, user_id: UserId, timezone: Option<Tz>) -> sqlx::Result<bool> {
+    let result = if let Some(timezone) = timezone {
+        sqlx::query!("UPDATE people SET data = jsonb_set(COALESCE(data, '{}'), '{timezone}', $1) WHERE snowflake = $2", json!(timezone.name()), i64::from(user_id)).execute(pool).await?
+    } else {
+        sqlx::query!("UPDATE people SET data = data - 'timezone' WHERE snowflake = $1", i64::from(user_id)).execute(pool).await?
+    };
+    Ok(result.rows_affected() > 0)
+}
+
+/// Returns the Discord user ID of the Person currently using the given Minecraft nickname, if any.
+pub async fn from_minecraft(pool: &PgPool, minecraft_nick: &str) -> sqlx::Result<Option<UserId>> {
+    Ok(sqlx::query_scalar!(r#"SELECT snowflake FROM people WHERE data->'minecraft'->'nicks'->>-1 = $1"#, minecraft_nick).fetch_optional(pool).await?
+        .flatten()
+        .map(|snowflake| UserId::new(snowflake as u64)))
+}
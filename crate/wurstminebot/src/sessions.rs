@@ -0,0 +1,70 @@
+//! Tracking of player sessions, i.e. periods during which a player is online in a world.
+
+use {
+    chrono::prelude::*,
+    serde::Serialize,
+    sqlx::PgPool,
+    systemd_minecraft::World,
+};
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Session {
+    pub minecraft_nick: String,
+    pub world: String,
+    pub start_time: DateTime<Utc>,
+    /// `None` if the player is still online.
+    pub end_time: Option<DateTime<Utc>>,
+}
+
+impl Session {
+    /// Returns all sessions, optionally restricted to the given world, ordered by start time.
+    pub async fn all(pool: &PgPool, world: Option<&str>) -> sqlx::Result<Vec<Self>> {
+        sqlx::query_as!(Self, "SELECT minecraft_nick, world, start_time, end_time FROM sessions WHERE $1::TEXT IS NULL OR world = $1 ORDER BY start_time", world).fetch_all(pool).await
+    }
+}
+
+/// Records that the given player has joined the given world at the given time.
+pub async fn start(pool: &PgPool, world: &World, minecraft_nick: &str, start_time: DateTime<Utc>) -> sqlx::Result<()> {
+    let mut transaction = pool.begin().await?;
+    // a player can't be online twice, so a session which is still open must have been missed
+    sqlx::query!("UPDATE sessions SET end_time = $3 WHERE world = $1 AND minecraft_nick = $2 AND end_time IS NULL", world.to_string(), minecraft_nick, start_time).execute(&mut *transaction).await?;
+    sqlx::query!("INSERT INTO sessions (minecraft_nick, world, start_time) VALUES ($1, $2, $3)", minecraft_nick, world.to_string(), start_time).execute(&mut *transaction).await?;
+    transaction.commit().await
+}
+
+/// Records that the given player has left the given world at the given time.
+pub async fn end(pool: &PgPool, world: &World, minecraft_nick: &str, end_time: DateTime<Utc>) -> sqlx::Result<()> {
+    sqlx::query!("UPDATE sessions SET end_time = $3 WHERE world = $1 AND minecraft_nick = $2 AND end_time IS NULL", world.to_string(), minecraft_nick, end_time).execute(pool).await?;
+    Ok(())
+}
+
+/// Makes the open sessions of the given world match the given list of players who are currently online.
+///
+/// Sessions of players who aren't online anymore are closed and sessions are started for online players who don't have an open one. This is used at server start (with an empty list) and at bot startup, since leave lines may have been missed in the meantime.
+pub async fn reconcile(pool: &PgPool, world: &World, online: &[String]) -> sqlx::Result<()> {
+    let mut transaction = pool.begin().await?;
+    sqlx::query!("UPDATE sessions SET end_time = NOW() WHERE world = $1 AND end_time IS NULL AND NOT (minecraft_nick = ANY($2))", world.to_string(), online).execute(&mut *transaction).await?;
+    for minecraft_nick in online {
+        sqlx::query!("INSERT INTO sessions (minecraft_nick, world, start_time) SELECT $1, $2, NOW() WHERE NOT EXISTS (SELECT 1 FROM sessions WHERE world = $2 AND minecraft_nick = $1 AND end_time IS NULL)", minecraft_nick, world.to_string()).execute(&mut *transaction).await?;
+    }
+    transaction.commit().await
+}
+
+/// Returns the Minecraft nicknames of all players who have played on the given world (or on any world if `None` is given), along with their total playtime in seconds, ordered by playtime descending.
+pub async fn leaderboard(pool: &PgPool, world: Option<&str>) -> sqlx::Result<Vec<(String, i64)>> {
+    Ok(sqlx::query!(r#"SELECT minecraft_nick, EXTRACT(EPOCH FROM SUM(COALESCE(end_time, NOW()) - start_time))::BIGINT AS "playtime!" FROM sessions WHERE $1::TEXT IS NULL OR world = $1 GROUP BY minecraft_nick ORDER BY 2 DESC"#, world).fetch_all(pool).await?
+        .into_iter()
+        .map(|row| (row.minecraft_nick, row.playtime))
+        .collect())
+}
+
+/// Formats a duration given in seconds as hours and minutes, e.g. `12h 34m`.
+pub fn format_playtime(seconds: i64) -> String {
+    let minutes = seconds / 60;
+    if minutes < 60 {
+        format!("{minutes}m")
+    } else {
+        format!("{}h {}m", minutes / 60, minutes % 60)
+    }
+}
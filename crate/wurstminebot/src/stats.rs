@@ -0,0 +1,63 @@
+//! Statistics about the deaths and advancements recorded from the world logs.
+
+use {
+    chrono::prelude::*,
+    sqlx::PgPool,
+};
+
+pub struct Death {
+    pub world: String,
+    pub timestamp: DateTime<Utc>,
+    pub msg: String,
+}
+
+pub struct Advancement {
+    pub world: String,
+    pub timestamp: DateTime<Utc>,
+    pub player: String,
+    pub advancement: String,
+}
+
+/// Returns the number of deaths of the given player (or of all players if `None` is given) for each death message translation key, ordered by count descending.
+///
+/// Deaths which were recorded without a translation key are not included.
+pub async fn deaths_by_cause(pool: &PgPool, player: Option<&str>) -> sqlx::Result<Vec<(String, i64)>> {
+    Ok(sqlx::query!(r#"SELECT key AS "key!", COUNT(*) AS "count!" FROM deaths WHERE key IS NOT NULL AND ($1::TEXT IS NULL OR victim = $1) GROUP BY key ORDER BY 2 DESC, 1"#, player).fetch_all(pool).await?
+        .into_iter()
+        .map(|row| (row.key, row.count))
+        .collect())
+}
+
+/// Returns the most recent deaths of the given player (or of all players if `None` is given), most recent first.
+pub async fn recent_deaths(pool: &PgPool, player: Option<&str>, limit: i64) -> sqlx::Result<Vec<Death>> {
+    sqlx::query_as!(Death, "SELECT world, timestamp, msg FROM deaths WHERE $1::TEXT IS NULL OR victim = $1 ORDER BY timestamp DESC LIMIT $2", player, limit).fetch_all(pool).await
+}
+
+/// Returns the number of deaths of each player in each world as `(world, player, count)`, ordered by world and then by count descending. If a player is given, only their deaths are counted.
+pub async fn death_leaderboard(pool: &PgPool, player: Option<&str>) -> sqlx::Result<Vec<(String, String, i64)>> {
+    Ok(sqlx::query!(r#"SELECT world, victim AS "victim!", COUNT(*) AS "count!" FROM deaths WHERE victim IS NOT NULL AND ($1::TEXT IS NULL OR victim = $1) GROUP BY world, victim ORDER BY world, 3 DESC, victim"#, player).fetch_all(pool).await?
+        .into_iter()
+        .map(|row| (row.world, row.victim, row.count))
+        .collect())
+}
+
+/// Returns the number of advancements made by the given player (or by all players if `None` is given) for each advancement kind, ordered by count descending.
+pub async fn advancements_by_kind(pool: &PgPool, player: Option<&str>) -> sqlx::Result<Vec<(String, i64)>> {
+    Ok(sqlx::query!(r#"SELECT kind, COUNT(*) AS "count!" FROM advancements WHERE $1::TEXT IS NULL OR player = $1 GROUP BY kind ORDER BY 2 DESC, 1"#, player).fetch_all(pool).await?
+        .into_iter()
+        .map(|row| (row.kind, row.count))
+        .collect())
+}
+
+/// Returns the most recent advancements made by the given player (or by all players if `None` is given), most recent first.
+pub async fn recent_advancements(pool: &PgPool, player: Option<&str>, limit: i64) -> sqlx::Result<Vec<Advancement>> {
+    sqlx::query_as!(Advancement, "SELECT world, timestamp, player, advancement FROM advancements WHERE $1::TEXT IS NULL OR player = $1 ORDER BY timestamp DESC LIMIT $2", player, limit).fetch_all(pool).await
+}
+
+/// Returns the number of distinct advancements of each player in each world as `(world, player, count)`, ordered by world and then by count descending. If a player is given, only their advancements are counted.
+pub async fn advancement_leaderboard(pool: &PgPool, player: Option<&str>) -> sqlx::Result<Vec<(String, String, i64)>> {
+    Ok(sqlx::query!(r#"SELECT world, player, COUNT(DISTINCT advancement) AS "count!" FROM advancements WHERE $1::TEXT IS NULL OR player = $1 GROUP BY world, player ORDER BY world, 3 DESC, player"#, player).fetch_all(pool).await?
+        .into_iter()
+        .map(|row| (row.world, row.player, row.count))
+        .collect())
+}
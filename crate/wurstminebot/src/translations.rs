@@ -0,0 +1,260 @@
+//! Loading the English translations of a Minecraft version, which are used to recognize death messages and advancements in the logs.
+
+use {
+    std::{
+        collections::HashMap,
+        path::{
+            Path,
+            PathBuf,
+        },
+    },
+    futures::prelude::*,
+    serde::Deserialize,
+    systemd_minecraft::World,
+    tokio::io,
+    tokio_util::io::StreamReader,
+    url::Url,
+    wheel::{
+        fs::{
+            self,
+            File,
+        },
+        io_error_from_reqwest,
+        traits::ReqwestResponseExt as _,
+    },
+};
+
+const LANG_FILE: &str = "assets/minecraft/lang/en_us.json";
+/// The language file of versions before 1.13, in `key=value` format.
+const LEGACY_LANG_FILE: &str = "assets/minecraft/lang/en_US.lang";
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)] Io(#[from] io::Error),
+    #[error(transparent)] Json(#[from] serde_json::Error),
+    #[error(transparent)] Reqwest(#[from] reqwest::Error),
+    #[error(transparent)] Wheel(#[from] wheel::Error),
+    #[error(transparent)] Zip(#[from] async_zip::error::ZipError),
+    #[error("no en_us language file in Minecraft client jar")]
+    MissingLangFile,
+    #[error("Minecraft version not found in launcher manifest")]
+    MissingVersion,
+    #[error("no translations found for Minecraft version {0}")]
+    NotFound(String),
+}
+
+/// A place from which the English translations for a Minecraft version can be loaded.
+pub enum Source {
+    /// A directory with files named `<version>.json`, for custom and modded versions. Configured as `translationsDir`.
+    Override(PathBuf),
+    /// Translations which have previously been loaded from another source.
+    Cache,
+    /// The server jar in the world directory. Bundler jars (1.18 and later) are searched for the nested server jar.
+    ServerJar(PathBuf),
+    /// The launcher's copy of the client jar.
+    ClientJar,
+    /// The launcher's asset index. Vanilla versions ship the English language file in the client jar rather than as an asset, but some launchers and modpacks add it.
+    AssetIndex,
+    /// The client jar, downloaded from Mojang to where [`Source::ClientJar`] finds it.
+    Download(reqwest::Client),
+}
+
+/// The sources which are tried in order to load the translations for the given world.
+pub fn sources(http_client: reqwest::Client, override_dir: Option<PathBuf>, world: &World) -> Vec<Source> {
+    let mut sources = Vec::default();
+    if let Some(override_dir) = override_dir {
+        sources.push(Source::Override(override_dir));
+    }
+    sources.push(Source::Cache);
+    sources.push(Source::ServerJar(world.dir()));
+    sources.push(Source::ClientJar);
+    sources.push(Source::AssetIndex);
+    sources.push(Source::Download(http_client));
+    sources
+}
+
+fn cache_path(version: &str) -> PathBuf {
+    crate::base_path().join("cache").join("translations").join(format!("{version}.json"))
+}
+
+fn launcher_dir() -> PathBuf {
+    crate::base_path().join("home").join(".minecraft-wurstmineberg")
+}
+
+fn client_jar_path(version: &str) -> PathBuf {
+    launcher_dir().join("versions").join(version).join(format!("{version}.jar"))
+}
+
+async fn read_json_file(path: &Path) -> Result<Option<HashMap<String, String>>, Error> {
+    Ok(if fs::exists(path).await? {
+        Some(serde_json::from_str(&fs::read_to_string(path).await?)?)
+    } else {
+        None
+    })
+}
+
+/// Parses a language file in the `key=value` format used before Minecraft 1.13.
+fn parse_legacy_lang(contents: &str) -> HashMap<String, String> {
+    contents.lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.to_owned(), value.to_owned()))
+        .collect()
+}
+
+/// Parses the language file with the given name in a jar, which is either [`LANG_FILE`] or [`LEGACY_LANG_FILE`].
+fn parse_lang_file(filename: &str, contents: &str) -> Result<HashMap<String, String>, Error> {
+    Ok(if filename == LEGACY_LANG_FILE {
+        parse_legacy_lang(contents)
+    } else {
+        serde_json::from_str(contents)?
+    })
+}
+
+/// Reads the language file from a jar, or from the jar nested in it in case of a bundler jar.
+async fn read_jar(path: &Path) -> Result<Option<HashMap<String, String>>, Error> {
+    let is_lang_file = |filename: &str| filename == LANG_FILE || filename == LEGACY_LANG_FILE;
+    if !fs::exists(path).await? { return Ok(None) }
+    let zip_file = async_zip::tokio::read::fs::ZipFileReader::new(path).await?;
+    let entries = zip_file.file().entries();
+    if let Some((index, filename)) = entries.iter().enumerate().find_map(|(index, entry)| entry.filename().as_str().ok().filter(|filename| is_lang_file(filename)).map(|filename| (index, filename.to_owned()))) {
+        let mut english = String::default();
+        zip_file.reader_with_entry(index).await?.read_to_string_checked(&mut english).await?;
+        return Ok(Some(parse_lang_file(&filename, &english)?))
+    }
+    if let Some(index) = entries.iter().position(|entry| entry.filename().as_str().map_or(false, |filename| filename.starts_with("META-INF/versions/") && filename.ends_with(".jar"))) {
+        let mut nested = Vec::default();
+        zip_file.reader_with_entry(index).await?.read_to_end_checked(&mut nested).await?;
+        let nested = async_zip::base::read::mem::ZipFileReader::new(nested).await?;
+        if let Some((index, filename)) = nested.file().entries().iter().enumerate().find_map(|(index, entry)| entry.filename().as_str().ok().filter(|filename| is_lang_file(filename)).map(|filename| (index, filename.to_owned()))) {
+            let mut english = String::default();
+            nested.reader_with_entry(index).await?.read_to_string_checked(&mut english).await?;
+            return Ok(Some(parse_lang_file(&filename, &english)?))
+        }
+    }
+    Ok(None)
+}
+
+impl Source {
+    /// Returns `Ok(None)` if this source doesn't have translations for the given version.
+    async fn load(&self, version: &str) -> Result<Option<HashMap<String, String>>, Error> {
+        match self {
+            Self::Override(dir) => read_json_file(&dir.join(format!("{version}.json"))).await,
+            Self::Cache => read_json_file(&cache_path(version)).await,
+            Self::ServerJar(world_dir) => read_jar(&world_dir.join(format!("minecraft_server.{version}.jar"))).await,
+            Self::ClientJar => read_jar(&client_jar_path(version)).await,
+            Self::AssetIndex => {
+                #[derive(Deserialize)]
+                #[serde(rename_all = "camelCase")]
+                struct VersionInfo {
+                    asset_index: VersionInfoAssetIndex,
+                }
+
+                #[derive(Deserialize)]
+                struct VersionInfoAssetIndex {
+                    id: String,
+                }
+
+                #[derive(Deserialize)]
+                struct AssetIndex {
+                    objects: HashMap<String, AssetIndexObject>,
+                }
+
+                #[derive(Deserialize)]
+                struct AssetIndexObject {
+                    hash: String,
+                }
+
+                let version_info_path = launcher_dir().join("versions").join(version).join(format!("{version}.json"));
+                if !fs::exists(&version_info_path).await? { return Ok(None) }
+                let version_info = serde_json::from_str::<VersionInfo>(&fs::read_to_string(&version_info_path).await?)?;
+                let asset_index_path = launcher_dir().join("assets").join("indexes").join(format!("{}.json", version_info.asset_index.id));
+                if !fs::exists(&asset_index_path).await? { return Ok(None) }
+                let asset_index = serde_json::from_str::<AssetIndex>(&fs::read_to_string(&asset_index_path).await?)?;
+                let object_path = |object: &AssetIndexObject| launcher_dir().join("assets").join("objects").join(&object.hash[..2]).join(&object.hash);
+                if let Some(object) = asset_index.objects.get("minecraft/lang/en_us.json") {
+                    read_json_file(&object_path(object)).await
+                } else if let Some(object) = asset_index.objects.get("minecraft/lang/en_us.lang") {
+                    let path = object_path(object);
+                    if !fs::exists(&path).await? { return Ok(None) }
+                    Ok(Some(parse_legacy_lang(&fs::read_to_string(&path).await?)))
+                } else {
+                    Ok(None)
+                }
+            }
+            Self::Download(http_client) => {
+                #[derive(Deserialize)]
+                struct VersionManifestInfo {
+                    id: String,
+                    url: Url,
+                }
+
+                #[derive(Deserialize)]
+                struct VersionManifest {
+                    versions: Vec<VersionManifestInfo>,
+                }
+
+                #[derive(Deserialize)]
+                struct VersionInfo {
+                    downloads: VersionInfoDownloads,
+                }
+
+                #[derive(Deserialize)]
+                struct VersionInfoDownloads {
+                    client: VersionInfoDownload,
+                }
+
+                #[derive(Deserialize)]
+                struct VersionInfoDownload {
+                    url: Url,
+                }
+
+                let client_jar_path = client_jar_path(version);
+                fs::create_dir_all(client_jar_path.parent().expect("client jar path has a parent")).await?;
+                let version_manifest = http_client.get("https://launchermeta.mojang.com/mc/game/version_manifest.json")
+                    .send().await?
+                    .detailed_error_for_status().await?
+                    .json_with_text_in_error::<VersionManifest>().await?;
+                let version_info = http_client.get(version_manifest.versions.into_iter().find(|iter_version| iter_version.id == version).ok_or(Error::MissingVersion)?.url)
+                    .send().await?
+                    .detailed_error_for_status().await?
+                    .json_with_text_in_error::<VersionInfo>().await?;
+                io::copy_buf(&mut StreamReader::new(http_client.get(version_info.downloads.client.url).send().await?.detailed_error_for_status().await?.bytes_stream().map_err(io_error_from_reqwest)), &mut File::create(&client_jar_path).await?).await?;
+                Ok(Some(read_jar(&client_jar_path).await?.ok_or(Error::MissingLangFile)?))
+            }
+        }
+    }
+}
+
+/// Writes translations to the cache. The file is written under a temporary name and then renamed, so an interrupted write can't leave a truncated cache file behind.
+async fn write_cache(version: &str, translations: &HashMap<String, String>) -> Result<(), Error> {
+    let cache_path = cache_path(version);
+    let temp_path = cache_path.with_extension("json.tmp");
+    fs::create_dir_all(cache_path.parent().expect("cache path has a parent")).await?;
+    fs::write(&temp_path, serde_json::to_vec(translations)?).await?;
+    tokio::fs::rename(&temp_path, &cache_path).await?;
+    Ok(())
+}
+
+/// Loads the English translations for the given Minecraft version from the first source which has them.
+///
+/// Translations loaded from the server jar, the launcher, or Mojang are written to the cache if possible. If no source has the translations, the error from the last source which failed is returned, so e.g. a failed download is reported if no local source has the version.
+pub async fn load(sources: &[Source], version: &str) -> Result<HashMap<String, String>, Error> {
+    let mut last_error = None;
+    for source in sources {
+        match source.load(version).await {
+            Ok(Some(translations)) => {
+                if !matches!(source, Source::Override(_) | Source::Cache) {
+                    // the translations are usable even if they can't be cached
+                    if let Err(e) = write_cache(version, &translations).await {
+                        eprintln!("failed to cache translations for Minecraft {version}: {e} ({e:?})");
+                    }
+                }
+                return Ok(translations)
+            }
+            Ok(None) => {}
+            Err(e) => last_error = Some(e),
+        }
+    }
+    Err(last_error.unwrap_or_else(|| Error::NotFound(version.to_owned())))
+}